@@ -0,0 +1,417 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Trend descriptors such as the peak year, the first attestation, growth and
+//! change points, and ranking of search results by them.
+
+use crate::{Client, Corpus, Error, Measure, Ngram, NgramLite, TimeSeries, TotalCounts, YearRange};
+use std::cmp::Ordering;
+
+/// Threshold for counting an ngram as attested. Isolated matches, e.g. from
+/// OCR errors or misdated books, are ignored by requiring a minimum match
+/// count in several consecutive years.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    pub min_match_count: u64,
+    pub consecutive_years: usize,
+}
+
+impl Default for Attestation {
+    fn default() -> Self {
+        Self {
+            min_match_count: 5,
+            consecutive_years: 3,
+        }
+    }
+}
+
+/// Absolute and relative time series of an ngram over all corpus years.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trend {
+    absolute: TimeSeries,
+    relative: TimeSeries,
+}
+
+impl Trend {
+    /// Panics if the ngram does not have 1 to 5 tokens.
+    pub fn new(ngram: &Ngram, totals: &TotalCounts) -> Self {
+        let absolute = ngram.time_series(Measure::Absolute).reindex(totals.years());
+        let relative = absolute.relative_to_total(totals, ngram.expect_len());
+        Self { absolute, relative }
+    }
+
+    pub fn absolute(&self) -> &TimeSeries {
+        &self.absolute
+    }
+
+    pub fn relative(&self) -> &TimeSeries {
+        &self.relative
+    }
+
+    /// Year of the highest relative frequency, or `None` if the ngram never
+    /// occurs. Ties go to the earliest year.
+    pub fn peak_year(&self) -> Option<u16> {
+        self.relative.peak().map(|(year, _)| year)
+    }
+
+    /// First year of the first run of `threshold.consecutive_years` years that
+    /// each have at least `threshold.min_match_count` matches.
+    pub fn first_attestation(&self, threshold: Attestation) -> Option<u16> {
+        let consecutive_years = threshold.consecutive_years.max(1);
+        let mut run = 0;
+        for (year, count) in &self.absolute {
+            if count >= threshold.min_match_count as f64 {
+                run += 1;
+                if run == consecutive_years {
+                    return Some(year + 1 - run as u16);
+                }
+            } else {
+                run = 0;
+            }
+        }
+        None
+    }
+
+    /// See [`TimeSeries::slope`], applied to the relative frequencies.
+    pub fn slope<R: Into<YearRange>>(&self, years: R) -> Option<f64> {
+        self.relative.slope(years)
+    }
+
+    /// See [`TimeSeries::doubling_time`], applied to the relative frequencies.
+    pub fn doubling_time<R: Into<YearRange>>(&self, years: R) -> Option<f64> {
+        self.relative.doubling_time(years)
+    }
+
+    /// See [`TimeSeries::change_points`], applied to the relative frequencies.
+    pub fn change_points(&self, min_segment_len: usize) -> Vec<u16> {
+        self.relative.change_points(min_segment_len)
+    }
+}
+
+impl TimeSeries {
+    /// Year and value of the maximum, or `None` if no value is positive. Ties
+    /// go to the earliest year.
+    pub fn peak(&self) -> Option<(u16, f64)> {
+        self.iter()
+            .filter(|&(_, value)| value > 0.0)
+            .fold(None, |peak, (year, value)| match peak {
+                Some((_, max)) if max >= value => peak,
+                _ => Some((year, value)),
+            })
+    }
+
+    /// Least-squares slope in units per year within `years`, or `None` if
+    /// fewer than two years of the series are within `years`.
+    pub fn slope<R: Into<YearRange>>(&self, years: R) -> Option<f64> {
+        let points: Vec<_> = self.slice(years).iter().collect();
+        linear_fit(&points).map(|(slope, _)| slope)
+    }
+
+    /// Number of years it takes the series to double, based on an exponential
+    /// fit within `years`. Years with a value of zero are ignored. Returns
+    /// `None` if fewer than two years have a positive value or if the series
+    /// is not growing.
+    pub fn doubling_time<R: Into<YearRange>>(&self, years: R) -> Option<f64> {
+        let points: Vec<_> = self
+            .slice(years)
+            .iter()
+            .filter(|&(_, value)| value > 0.0)
+            .map(|(year, value)| (year, value.ln()))
+            .collect();
+        match linear_fit(&points) {
+            Some((rate, _)) if rate > 0.0 => Some(std::f64::consts::LN_2 / rate),
+            _ => None,
+        }
+    }
+
+    /// Years in which the mean of the series shifts, found by binary
+    /// segmentation. A split is accepted if it reduces the squared error by
+    /// more than the BIC penalty `2 σ² ln(n)`, where the noise level σ is
+    /// estimated from the year-over-year differences. Each segment spans at
+    /// least `min_segment_len` years. The returned years, in ascending order,
+    /// are the first years of the new segments.
+    pub fn change_points(&self, min_segment_len: usize) -> Vec<u16> {
+        let Some(years) = self.years() else {
+            return Vec::new();
+        };
+        let values = self.values();
+        let penalty = 2.0 * noise_variance(values) * (values.len() as f64).ln();
+        let mut change_points = Vec::new();
+        if penalty > 0.0 {
+            let segments = Segments::new(values);
+            segments.split(
+                0,
+                values.len(),
+                min_segment_len.max(1),
+                penalty,
+                &mut change_points,
+            );
+        }
+        change_points.sort_unstable();
+        change_points
+            .into_iter()
+            .map(|i| years.first() + i as u16)
+            .collect()
+    }
+}
+
+/// Returns `(slope, intercept)` of the least-squares line through `points`.
+fn linear_fit(points: &[(u16, f64)]) -> Option<(f64, f64)> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x as f64).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let (sxy, sxx) = points.iter().fold((0.0, 0.0), |(sxy, sxx), &(x, y)| {
+        let dx = x as f64 - mean_x;
+        (sxy + dx * (y - mean_y), sxx + dx * dx)
+    });
+    let slope = sxy / sxx;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+/// Robust noise variance from the median absolute deviation of the first
+/// differences. Falls back to the mean squared difference for step functions,
+/// whose median deviation is zero.
+fn noise_variance(values: &[f64]) -> f64 {
+    let diffs: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    if diffs.is_empty() {
+        return 0.0;
+    }
+    let center = median(diffs.clone());
+    let mad = median(diffs.iter().map(|d| (d - center).abs()).collect());
+    let sigma = 1.4826 * mad / std::f64::consts::SQRT_2;
+    if sigma > 0.0 {
+        sigma * sigma
+    } else {
+        diffs.iter().map(|d| d * d).sum::<f64>() / diffs.len() as f64 / 2.0
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_unstable_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Prefix sums for constant-time squared errors of segments.
+struct Segments {
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl Segments {
+    fn new(values: &[f64]) -> Self {
+        let mut sums = vec![0.0];
+        let mut squares = vec![0.0];
+        for value in values {
+            sums.push(sums.last().unwrap() + value);
+            squares.push(squares.last().unwrap() + value * value);
+        }
+        Self { sums, squares }
+    }
+
+    /// Squared error of `values[start..end]` around its mean.
+    fn cost(&self, start: usize, end: usize) -> f64 {
+        let n = (end - start) as f64;
+        let sum = self.sums[end] - self.sums[start];
+        (self.squares[end] - self.squares[start]) - sum * sum / n
+    }
+
+    fn split(&self, start: usize, end: usize, min_len: usize, penalty: f64, out: &mut Vec<usize>) {
+        if end - start < 2 * min_len {
+            return;
+        }
+        let total = self.cost(start, end);
+        let best = (start + min_len..=end - min_len)
+            .map(|i| (i, total - self.cost(start, i) - self.cost(i, end)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, gain)) = best {
+            if gain > penalty {
+                out.push(i);
+                self.split(start, i, min_len, penalty, out);
+                self.split(i, end, min_len, penalty, out);
+            }
+        }
+    }
+}
+
+/// Descriptor to rank ngrams by, see [`Client::rank_by_trend`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RankBy {
+    /// Steepest increase of the relative frequency first.
+    Slope(YearRange),
+    /// Shortest doubling time of the relative frequency first.
+    DoublingTime(YearRange),
+    /// Most recent peak year first.
+    PeakYear,
+    /// Most recent first attestation first.
+    FirstAttestation(Attestation),
+}
+
+impl RankBy {
+    fn score(&self, trend: &Trend) -> Option<f64> {
+        match *self {
+            RankBy::Slope(years) => trend.slope(years),
+            RankBy::DoublingTime(years) => trend.doubling_time(years),
+            RankBy::PeakYear => trend.peak_year().map(f64::from),
+            RankBy::FirstAttestation(threshold) => {
+                trend.first_attestation(threshold).map(f64::from)
+            }
+        }
+    }
+
+    fn cmp(&self, a: f64, b: f64) -> Ordering {
+        match self {
+            RankBy::DoublingTime(_) => a.total_cmp(&b),
+            _ => b.total_cmp(&a),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ranked {
+    pub ngram: Ngram,
+    pub trend: Trend,
+    /// Value of the [`RankBy`] descriptor, `None` if it is undefined for this
+    /// ngram, e.g. the doubling time of a declining ngram.
+    pub score: Option<f64>,
+}
+
+impl Client {
+    /// Fetches the full stats of `ngrams` and ranks them by `by`. Ngrams
+    /// without a score come last, ngrams that no longer exist are skipped.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ngrams::Client) -> Result<(), ngrams::Error> {
+    /// use ngrams::analytics::RankBy;
+    /// use ngrams::{Corpus, SearchOptions, YearRange};
+    ///
+    /// let totals = client.get_total_counts(Corpus::English).await?;
+    /// let mut pages = client.search("climate *", Corpus::English, SearchOptions::default());
+    /// let page = pages.next().await.unwrap()?.to_page();
+    /// let since_1990 = RankBy::Slope(YearRange::new(1990, totals.max_year));
+    /// let ranked = client
+    ///     .rank_by_trend(Corpus::English, &page.ngrams, &totals, since_1990)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn rank_by_trend<'a, I>(
+        &self,
+        corpus: Corpus,
+        ngrams: I,
+        totals: &TotalCounts,
+        by: RankBy,
+    ) -> Result<Vec<Ranked>, Error>
+    where
+        I: IntoIterator<Item = &'a NgramLite>,
+    {
+        let mut ranked = Vec::new();
+        for lite in ngrams {
+            let Some(ngram) = self.get_ngram(corpus, &lite.id).await? else {
+                continue;
+            };
+            let trend = Trend::new(&ngram, totals);
+            let score = by.score(&trend);
+            ranked.push(Ranked {
+                ngram,
+                trend,
+                score,
+            });
+        }
+        ranked.sort_by(|a, b| match (a.score, b.score) {
+            (Some(a), Some(b)) => by.cmp(a, b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        Ok(ranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attestation, RankBy, Trend};
+    use crate::testing::{ngram, tokens, total_counts, Fixtures};
+    use crate::{Client, Corpus, NgramLite, NgramStat, TimeSeries, YearRange};
+
+    #[test]
+    fn trend_descriptors() {
+        let totals = total_counts(1900, &[100; 10]);
+        let mut stats = vec![NgramStat::new(1900, 9, 0.0)];
+        stats.extend((1903..1910).map(|year| NgramStat::new(year, 1 << (year - 1903), 0.0)));
+        let mut ngram = ngram(stats);
+        ngram.tokens = tokens(&["x"]);
+        let trend = Trend::new(&ngram, &totals);
+
+        assert_eq!(trend.relative().years(), Some(totals.years()));
+        assert_eq!(trend.peak_year(), Some(1909));
+        assert_eq!(trend.first_attestation(Attestation::default()), Some(1906));
+        let doubling_time = trend.doubling_time(1903..=1909).unwrap();
+        assert!((doubling_time - 1.0).abs() < 1e-9);
+        assert!(trend.slope(1903..=1909).unwrap() > 0.0);
+        assert_eq!(trend.slope(1800..=1850), None);
+        assert_eq!(trend.doubling_time(1909..=1920), None);
+    }
+
+    #[test]
+    fn change_points_of_steps() {
+        let mut values = vec![1.0; 10];
+        values.extend([5.0; 10]);
+        values.extend([2.0; 10]);
+        let series = TimeSeries::new(1900, values);
+        assert_eq!(series.change_points(3), [1910, 1920]);
+        assert!(TimeSeries::new(1900, vec![1.0; 10])
+            .change_points(1)
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn rank_by_slope() {
+        let totals = total_counts(1900, &[100; 10]);
+        let rising = ngram(
+            (1900..1910)
+                .map(|y| NgramStat::new(y, y as u64 - 1900, 0.0))
+                .collect(),
+        );
+        let falling = ngram(
+            (1900..1910)
+                .map(|y| NgramStat::new(y, 1910 - y as u64, 0.0))
+                .collect(),
+        );
+        let ngrams: Vec<_> = [("rising", rising), ("falling", falling)]
+            .into_iter()
+            .map(|(id, mut ngram)| {
+                ngram.id = id.to_string();
+                ngram.tokens = tokens(&[id]);
+                ngram
+            })
+            .collect();
+        let lites: Vec<_> = ["falling", "missing", "rising"]
+            .into_iter()
+            .map(|id| NgramLite {
+                id: id.to_string(),
+                abs_total_match_count: 0,
+                rel_total_match_count: 0.0,
+                tokens: tokens(&[id]),
+                r#abstract: false,
+            })
+            .collect();
+        let client =
+            Client::with_transport(Fixtures::default().ngram(&ngrams[0]).ngram(&ngrams[1]));
+
+        let by = RankBy::Slope(YearRange::new(1900, 1909));
+        let ranked = client
+            .rank_by_trend(Corpus::English, &lites, &totals, by)
+            .await
+            .unwrap();
+        let ids: Vec<_> = ranked.iter().map(|r| r.ngram.id.as_str()).collect();
+        assert_eq!(ids, ["rising", "falling"]);
+        assert!(ranked[0].score.unwrap() > 0.0);
+    }
+}
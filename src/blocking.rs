@@ -0,0 +1,476 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Blocking (synchronous) facade over the async [`Client`](crate::Client).
+//!
+//! Requests are executed on a runtime owned by the client and running on a
+//! dedicated background thread. Calling into a blocking client from inside
+//! another async runtime is therefore safe, although it blocks the calling
+//! thread like any other blocking I/O.
+//!
+//! Every async method of the client has a blocking counterpart here, and so
+//! do the caching [`RarityProfiler`] and [`NgramLanguageModel`]. Two async
+//! helpers are not mirrored: a [`Typeahead`](crate::complete::Typeahead)
+//! cannot supersede a call that blocks, and instead of
+//! [`Pivot::add_pages`](crate::pivot::Pivot::add_pages) the pages of a
+//! blocking search are passed to `add_page` one by one.
+
+use crate::analytics::{RankBy, Ranked};
+use crate::collocation::{CollocationOptions, Collocations};
+use crate::compare::Alternatives;
+use crate::complete::{CompleteOptions, Completion};
+use crate::dating::{Dating, DatingOptions};
+use crate::frequency::YearRangeError;
+use crate::language_model::{LanguageModelOptions, Prediction};
+use crate::metrics::MetricsSnapshot;
+use crate::middleware::Middleware;
+use crate::orthography::SpellingHistory;
+use crate::pos::PosProfile;
+use crate::rarity::Profile;
+use crate::variants::{Normalization, VariantGroup};
+use crate::{
+    language_model, rarity, Corpus, CorpusInfo, Error, ErrorKind, Ngram, NgramLite, Page,
+    PartOfSpeech, SearchOptions, TotalCounts,
+};
+use std::future::Future;
+use std::sync::{mpsc, Arc};
+use std::{io, thread};
+use tokio::sync::oneshot;
+
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    #[cfg(feature = "reqwest")]
+    pub fn new() -> Self {
+        Self::from_async(crate::Client::new())
+    }
+
+    /// Wraps an existing async client, e.g. one built with
+    /// [`Client::from_service`](crate::Client::from_service). The async client
+    /// should not be used on another runtime at the same time.
+    pub fn from_async(inner: crate::Client) -> Self {
+        Self {
+            inner,
+            runtime: Arc::new(Runtime::new().expect("failed to start blocking runtime")),
+        }
+    }
+
+    pub fn with_middleware<M: Middleware + 'static>(self, middleware: M) -> Self {
+        Self {
+            inner: self.inner.with_middleware(middleware),
+            ..self
+        }
+    }
+
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.inner.metrics()
+    }
+
+    pub fn search<Q: Into<String>>(
+        &self,
+        query: Q,
+        corpus: Corpus,
+        options: SearchOptions,
+    ) -> Pages {
+        Pages {
+            inner: Some(self.inner.search(query, corpus, options)),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get_ngram(&self, corpus: Corpus, id: &str) -> Result<Option<Ngram>, Error> {
+        let client = self.inner.clone();
+        let id = id.to_string();
+        self.runtime
+            .block_on(async move { client.get_ngram(corpus, &id).await })?
+    }
+
+    pub fn get_corpus_info(&self, corpus: Corpus) -> Result<CorpusInfo, Error> {
+        let client = self.inner.clone();
+        self.runtime
+            .block_on(async move { client.get_corpus_info(corpus).await })?
+    }
+
+    pub fn get_total_counts(&self, corpus: Corpus) -> Result<TotalCounts, Error> {
+        let client = self.inner.clone();
+        self.runtime
+            .block_on(async move { client.get_total_counts(corpus).await })?
+    }
+
+    pub fn rank_by_trend<'a, I>(
+        &self,
+        corpus: Corpus,
+        ngrams: I,
+        totals: &TotalCounts,
+        by: RankBy,
+    ) -> Result<Vec<Ranked>, Error>
+    where
+        I: IntoIterator<Item = &'a NgramLite>,
+    {
+        let client = self.inner.clone();
+        let ngrams: Vec<_> = ngrams.into_iter().cloned().collect();
+        let totals = totals.clone();
+        self.runtime
+            .block_on(async move { client.rank_by_trend(corpus, &ngrams, &totals, by).await })?
+    }
+
+    pub fn collocations(
+        &self,
+        node: &str,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: &CollocationOptions,
+    ) -> Result<Collocations, Error> {
+        let client = self.inner.clone();
+        let node = node.to_string();
+        let totals = totals.clone();
+        let options = options.clone();
+        self.runtime
+            .block_on(async move { client.collocations(&node, corpus, &totals, &options).await })?
+    }
+
+    pub fn pos_profile(&self, word: &str, corpus: Corpus) -> Result<PosProfile, Error> {
+        let client = self.inner.clone();
+        let word = word.to_string();
+        self.runtime
+            .block_on(async move { client.pos_profile(&word, corpus).await })?
+    }
+
+    pub fn complete(
+        &self,
+        phrase: &str,
+        corpus: Corpus,
+        k: usize,
+    ) -> Result<Vec<Completion>, Error> {
+        self.complete_with(phrase, corpus, k, CompleteOptions::default())
+    }
+
+    pub fn complete_with(
+        &self,
+        phrase: &str,
+        corpus: Corpus,
+        k: usize,
+        options: CompleteOptions,
+    ) -> Result<Vec<Completion>, Error> {
+        let client = self.inner.clone();
+        let phrase = phrase.to_string();
+        self.runtime
+            .block_on(async move { client.complete_with(&phrase, corpus, k, options).await })?
+    }
+
+    pub fn compare<I, S>(&self, corpus: Corpus, phrases: I) -> Result<Alternatives, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let client = self.inner.clone();
+        let phrases: Vec<_> = phrases
+            .into_iter()
+            .map(|phrase| phrase.as_ref().to_string())
+            .collect();
+        self.runtime
+            .block_on(async move { client.compare(corpus, phrases).await })?
+    }
+
+    pub fn date_text(
+        &self,
+        text: &str,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: &DatingOptions,
+    ) -> Result<Dating, Error> {
+        let client = self.inner.clone();
+        let text = text.to_string();
+        let totals = totals.clone();
+        let options = *options;
+        self.runtime
+            .block_on(async move { client.date_text(&text, corpus, &totals, &options).await })?
+    }
+
+    pub fn group_variants<'a, I>(
+        &self,
+        corpus: Corpus,
+        ngrams: I,
+        normalization: &Normalization,
+    ) -> Result<Vec<VariantGroup>, Error>
+    where
+        I: IntoIterator<Item = &'a NgramLite>,
+    {
+        let client = self.inner.clone();
+        let ngrams: Vec<_> = ngrams.into_iter().cloned().collect();
+        let normalization = normalization.clone();
+        self.runtime
+            .block_on(async move { client.group_variants(corpus, &ngrams, &normalization).await })?
+    }
+
+    pub fn spelling_history(&self, word: &str, corpus: Corpus) -> Result<SpellingHistory, Error> {
+        let client = self.inner.clone();
+        let word = word.to_string();
+        self.runtime
+            .block_on(async move { client.spelling_history(&word, corpus).await })?
+    }
+
+    pub fn lemma_group(
+        &self,
+        lemma: &str,
+        pos: PartOfSpeech,
+        corpus: Corpus,
+    ) -> Result<VariantGroup, Error> {
+        let client = self.inner.clone();
+        let lemma = lemma.to_string();
+        self.runtime
+            .block_on(async move { client.lemma_group(&lemma, pos, corpus).await })?
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over the result pages of a search.
+pub struct Pages {
+    inner: Option<crate::Pages>,
+    runtime: Arc<Runtime>,
+}
+
+impl Iterator for Pages {
+    type Item = Result<Page, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pages = self.inner.take()?;
+        let res = self.runtime.block_on(async move {
+            let page = pages.next().await.map(|res| res.map(|page| page.to_page()));
+            (pages, page)
+        });
+        match res {
+            Ok((pages, page)) => {
+                self.inner = Some(pages);
+                page
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Blocking [`rarity::RarityProfiler`].
+pub struct RarityProfiler {
+    /// Taken while a call runs on the runtime, and lost if it panicked.
+    inner: Option<rarity::RarityProfiler>,
+    runtime: Arc<Runtime>,
+}
+
+impl RarityProfiler {
+    pub fn new(client: Client, corpus: Corpus) -> Self {
+        Self {
+            inner: Some(rarity::RarityProfiler::new(client.inner, corpus)),
+            runtime: client.runtime,
+        }
+    }
+
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self {
+            inner: self.inner.map(|inner| inner.with_batch_size(batch_size)),
+            ..self
+        }
+    }
+
+    pub fn cache_len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.cache_len())
+    }
+
+    pub fn clear_cache(&mut self) {
+        if let Some(inner) = &mut self.inner {
+            inner.clear_cache();
+        }
+    }
+
+    pub fn profile(&mut self, text: &str, totals: &TotalCounts) -> Result<Profile, Error> {
+        let mut profiler = self.inner.take().ok_or_else(lost)?;
+        let text = text.to_string();
+        let totals = totals.clone();
+        let (profiler, res) = self.runtime.block_on(async move {
+            let res = profiler.profile(&text, &totals).await;
+            (profiler, res)
+        })?;
+        self.inner = Some(profiler);
+        res
+    }
+}
+
+/// Blocking [`language_model::NgramLanguageModel`].
+pub struct NgramLanguageModel {
+    /// Taken while a call runs on the runtime, and lost if it panicked.
+    inner: Option<language_model::NgramLanguageModel>,
+    runtime: Arc<Runtime>,
+}
+
+impl NgramLanguageModel {
+    /// Fails if `options.years` is not within the years of `totals`.
+    pub fn new(
+        client: Client,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: LanguageModelOptions,
+    ) -> Result<Self, YearRangeError> {
+        let inner = language_model::NgramLanguageModel::new(client.inner, corpus, totals, options)?;
+        Ok(Self {
+            inner: Some(inner),
+            runtime: client.runtime,
+        })
+    }
+
+    pub fn probability(&mut self, context: &str, word: &str) -> Result<f64, Error> {
+        let mut model = self.inner.take().ok_or_else(lost)?;
+        let context = context.to_string();
+        let word = word.to_string();
+        let (model, res) = self.runtime.block_on(async move {
+            let res = model.probability(&context, &word).await;
+            (model, res)
+        })?;
+        self.inner = Some(model);
+        res
+    }
+
+    pub fn predict(&mut self, context: &str, k: usize) -> Result<Vec<Prediction>, Error> {
+        let mut model = self.inner.take().ok_or_else(lost)?;
+        let context = context.to_string();
+        let (model, res) = self.runtime.block_on(async move {
+            let res = model.predict(&context, k).await;
+            (model, res)
+        })?;
+        self.inner = Some(model);
+        res
+    }
+
+    pub fn score(&mut self, sentence: &str) -> Result<f64, Error> {
+        let mut model = self.inner.take().ok_or_else(lost)?;
+        let sentence = sentence.to_string();
+        let (model, res) = self.runtime.block_on(async move {
+            let res = model.score(&sentence).await;
+            (model, res)
+        })?;
+        self.inner = Some(model);
+        res
+    }
+
+    pub fn cache_len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.cache_len())
+    }
+}
+
+/// Error for a profiler or model whose state was lost in a panicked call.
+fn lost() -> Error {
+    Error::new(
+        ErrorKind::Exception,
+        Some("state lost in a panicked call".into()),
+    )
+}
+
+/// Single-threaded runtime driven by a background thread that lives as long
+/// as the client and all of its clones.
+struct Runtime {
+    handle: tokio::runtime::Handle,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl Runtime {
+    fn new() -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        thread::Builder::new()
+            .name("ngrams-blocking".into())
+            .spawn(move || {
+                runtime.block_on(async {
+                    let _ = stopped.await;
+                })
+            })?;
+        Ok(Self {
+            handle,
+            _shutdown: shutdown,
+        })
+    }
+
+    /// Runs `future` on the background runtime and waits for its output.
+    fn block_on<F>(&self, future: F) -> Result<F::Output, Error>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.handle.spawn(async move {
+            let _ = tx.send(future.await);
+        });
+        rx.recv()
+            .map_err(|err| Error::new(ErrorKind::Exception, Some(Box::new(err))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, RarityProfiler};
+    use crate::testing::{page, total_counts, Canned, Fixtures};
+    use crate::{Corpus, NgramStat, SearchOptions};
+
+    #[test]
+    fn iterate_pages_synchronously() {
+        let client = Client::from_async(crate::Client::with_transport(Fixtures::default()))
+            .with_middleware(Canned);
+        let options = SearchOptions {
+            max_page_count: 3,
+            ..Default::default()
+        };
+        let pages: Vec<_> = client
+            .search("hello", Corpus::English, options)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].query_tokens[0].text, "hello");
+        assert_eq!(client.get_ngram(Corpus::English, "x").unwrap(), None);
+    }
+
+    #[test]
+    fn analyses_and_profilers_synchronously() {
+        let fixtures = Fixtures::default()
+            .found(
+                "(on the weekend)/(at the weekend)",
+                &[(
+                    &["on", "the", "weekend"],
+                    vec![NgramStat::new(1900, 5, 0.0)],
+                )],
+            )
+            .search("cup", &page(&[(&["cup"], 100)]));
+        let client = Client::from_async(crate::Client::with_transport(fixtures));
+
+        let alternatives = client
+            .compare(Corpus::English, ["on the weekend", "at the weekend"])
+            .unwrap();
+        assert_eq!(alternatives.candidates[0].phrase, "on the weekend");
+        assert!(!alternatives.candidates[1].is_attested());
+
+        let mut profiler = RarityProfiler::new(client.clone(), Corpus::English);
+        let totals = total_counts(1900, &[1000]);
+        let profile = profiler.profile("cup", &totals).unwrap();
+        assert_eq!(profile.spans[0].count, 100);
+        profiler.profile("cup", &totals).unwrap();
+        assert_eq!(profiler.cache_len(), 1);
+        assert_eq!(client.metrics().cache_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn call_from_inside_async_runtime() {
+        let client = Client::from_async(crate::Client::with_transport(Fixtures::default()))
+            .with_middleware(Canned);
+        assert_eq!(client.get_ngram(Corpus::English, "x").unwrap(), None);
+    }
+}
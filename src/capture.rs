@@ -0,0 +1,275 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Alignment of query tokens with the ngram tokens they matched.
+//!
+//! Every query token matches a contiguous span of ngram tokens: one token for
+//! terms and single wildcards, zero or more tokens for `**`, whose matches are
+//! flagged as `inserted`, and as many tokens as the chosen alternative has
+//! words for alternations and term groups. Wildcards, alternations and
+//! prefixes produce [`Capture`]s of the tokens they were filled with.
+//!
+//! The alignment works on owned results; convert a [`PageView`](crate::PageView)
+//! with [`PageView::to_page`](crate::PageView::to_page) first.
+
+use crate::{NgramLite, NgramToken, Page, QueryToken, QueryTokenKind};
+use std::ops::Range;
+
+/// Span of ngram tokens matched by each query token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alignment {
+    spans: Vec<Range<usize>>,
+}
+
+impl Alignment {
+    /// Returns `None` if `tokens` cannot be the result of `query`.
+    pub fn new(query: &[QueryToken], tokens: &[NgramToken]) -> Option<Self> {
+        let mut spans = Vec::with_capacity(query.len());
+        align(query, tokens, 0, &mut spans).then_some(Self { spans })
+    }
+
+    /// Span of ngram tokens matched by the query token at `index`. Panics if
+    /// `index` is out of bounds.
+    pub fn span(&self, index: usize) -> Range<usize> {
+        self.spans[index].clone()
+    }
+
+    /// Spans in the order of the query tokens.
+    pub fn spans(&self) -> &[Range<usize>] {
+        &self.spans
+    }
+}
+
+/// Ngram tokens that filled a wildcard, alternation or prefix of the query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capture<'a> {
+    /// Index of the query token.
+    pub index: usize,
+    pub query_token: &'a QueryToken,
+    /// Empty if a `**` matched nothing.
+    pub tokens: &'a [NgramToken],
+}
+
+impl Capture<'_> {
+    /// Texts of the captured tokens separated by a space.
+    pub fn text(&self) -> String {
+        let texts: Vec<_> = self
+            .tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect();
+        texts.join(" ")
+    }
+}
+
+impl NgramLite {
+    pub fn align(&self, query: &[QueryToken]) -> Option<Alignment> {
+        Alignment::new(query, &self.tokens)
+    }
+
+    /// Captures in the order of the query tokens, or `None` if the ngram
+    /// cannot be aligned with `query`.
+    pub fn captures<'a>(&'a self, query: &'a [QueryToken]) -> Option<Vec<Capture<'a>>> {
+        let alignment = self.align(query)?;
+        Some(
+            query
+                .iter()
+                .zip(alignment.spans)
+                .enumerate()
+                .filter(|(_, (query_token, _))| is_capturing(query_token.kind))
+                .map(|(index, (query_token, span))| Capture {
+                    index,
+                    query_token,
+                    tokens: &self.tokens[span],
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Page {
+    /// The `n`-th capture of each ngram, e.g. the word that filled the first
+    /// wildcard for `n == 0`. Ngrams that cannot be aligned are skipped.
+    pub fn captures(&self, n: usize) -> Vec<(&NgramLite, Capture<'_>)> {
+        self.ngrams
+            .iter()
+            .filter_map(|ngram| {
+                let capture = *ngram.captures(&self.query_tokens)?.get(n)?;
+                Some((ngram, capture))
+            })
+            .collect()
+    }
+}
+
+fn is_capturing(kind: QueryTokenKind) -> bool {
+    !matches!(
+        kind,
+        QueryTokenKind::Term
+            | QueryTokenKind::TermGroup
+            | QueryTokenKind::SentenceStart
+            | QueryTokenKind::SentenceEnd
+    )
+}
+
+/// Depth-first search for spans of `query` covering `tokens[start..]`.
+fn align(
+    query: &[QueryToken],
+    tokens: &[NgramToken],
+    start: usize,
+    spans: &mut Vec<Range<usize>>,
+) -> bool {
+    let Some((first, rest)) = query.split_first() else {
+        return start == tokens.len();
+    };
+    for len in span_lengths(first, &tokens[start..]) {
+        spans.push(start..start + len);
+        if align(rest, tokens, start + len, spans) {
+            return true;
+        }
+        spans.pop();
+    }
+    false
+}
+
+/// Candidate numbers of tokens `query_token` can match at the beginning of
+/// `tokens`, most likely first.
+fn span_lengths(query_token: &QueryToken, tokens: &[NgramToken]) -> Vec<usize> {
+    let fits = |len: usize| {
+        len <= tokens.len()
+            && tokens[..len].iter().all(|token| {
+                !token.inserted && (!token.completed || query_token.kind == QueryTokenKind::Prefix)
+            })
+    };
+    match query_token.kind {
+        QueryTokenKind::Starstar => vec![tokens.iter().take_while(|token| token.inserted).count()],
+        QueryTokenKind::Slash | QueryTokenKind::TermGroup => {
+            let mut lengths: Vec<(bool, usize)> = alternatives(&query_token.text)
+                .map(|words| {
+                    let len = words.len();
+                    let same_text = fits(len)
+                        && words
+                            .iter()
+                            .zip(tokens)
+                            .all(|(word, token)| word.to_lowercase() == token.text.to_lowercase());
+                    (!same_text, len)
+                })
+                .filter(|&(_, len)| fits(len))
+                .collect();
+            lengths.sort_unstable();
+            let mut lengths: Vec<_> = lengths.into_iter().map(|(_, len)| len).collect();
+            lengths.dedup();
+            lengths
+        }
+        _ => {
+            if fits(1) {
+                vec![1]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Words of each alternative of an alternation or term group.
+fn alternatives(text: &str) -> impl Iterator<Item = Vec<&str>> {
+    text.split('/').map(|alternative| {
+        alternative
+            .split(|c: char| c.is_whitespace() || matches!(c, '"' | '(' | ')'))
+            .filter(|word| !word.is_empty())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Alignment;
+    use crate::testing::{ngram_lite, tokens};
+    use crate::{NgramLite, NgramToken, Page, QueryToken, QueryTokenKind};
+
+    fn query(tokens: &[(QueryTokenKind, &str)]) -> Vec<QueryToken> {
+        tokens
+            .iter()
+            .map(|&(kind, text)| QueryToken {
+                kind,
+                text: text.to_string(),
+            })
+            .collect()
+    }
+
+    fn flagged(words: &[&str], inserted: &[usize], completed: &[usize]) -> Vec<NgramToken> {
+        let mut tokens = tokens(words);
+        for &i in inserted {
+            tokens[i].inserted = true;
+        }
+        for &i in completed {
+            tokens[i].completed = true;
+        }
+        tokens
+    }
+
+    #[test]
+    fn captures_of_wildcards() {
+        let page = Page {
+            query_tokens: query(&[
+                (QueryTokenKind::Term, "hello"),
+                (QueryTokenKind::Star, "*"),
+                (QueryTokenKind::Star, "*"),
+            ]),
+            ngrams: vec![
+                ngram_lite(&["hello", "my", "friend"], 3),
+                ngram_lite(&["hello", "world"], 2),
+            ],
+        };
+        let captures = page.captures(0);
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].1.text(), "my");
+        assert_eq!(captures[0].1.index, 1);
+        assert_eq!(page.captures(1)[0].1.text(), "friend");
+    }
+
+    #[test]
+    fn starstar_matches_inserted_tokens() {
+        let query = query(&[
+            (QueryTokenKind::Term, "a"),
+            (QueryTokenKind::Starstar, "**"),
+            (QueryTokenKind::Prefix, "b~"),
+            (QueryTokenKind::Star, "*"),
+        ]);
+        let ngram = NgramLite {
+            tokens: flagged(&["a", "x", "y", "bc", "d"], &[1, 2], &[3]),
+            ..ngram_lite(&[], 1)
+        };
+        let alignment = ngram.align(&query).unwrap();
+        assert_eq!(alignment.spans(), [0..1, 1..3, 3..4, 4..5]);
+        let captures = ngram.captures(&query).unwrap();
+        let texts: Vec<_> = captures.iter().map(|capture| capture.text()).collect();
+        assert_eq!(texts, ["x y", "bc", "d"]);
+
+        let empty = NgramLite {
+            tokens: flagged(&["a", "bc", "d"], &[], &[1]),
+            ..ngram_lite(&[], 1)
+        };
+        assert_eq!(empty.captures(&query).unwrap()[0].tokens, []);
+        assert_eq!(Alignment::new(&query, &tokens(&["a", "b"])), None);
+    }
+
+    #[test]
+    fn alternation_of_term_groups() {
+        let query = query(&[
+            (QueryTokenKind::Slash, "(new york)/boston"),
+            (QueryTokenKind::Star, "*"),
+        ]);
+        let ngram = ngram_lite(&["new", "york", "times"], 1);
+        let captures = ngram.captures(&query).unwrap();
+        assert_eq!(captures[0].text(), "new york");
+        assert_eq!(captures[1].text(), "times");
+        assert_eq!(
+            ngram_lite(&["boston", "globe"], 1)
+                .align(&query)
+                .unwrap()
+                .span(0),
+            0..1
+        );
+    }
+}
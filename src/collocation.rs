@@ -0,0 +1,339 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Collocation extraction from wildcard searches.
+
+use crate::compare::has_operators;
+use crate::pos::{PartOfSpeech, Tagged};
+use crate::significance::{Comparison, Sample};
+use crate::{Client, Corpus, Error, NgramLength, NgramTokenKind, SearchOptions, TotalCounts};
+use std::collections::HashMap;
+
+/// Which words co-occurring with a node word are collected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollocationOptions {
+    /// Position of the collocate relative to the node, from -4 to 4 except 0.
+    /// For example, 1 searches `strong *` and -1 searches `* tea`.
+    pub offset: i8,
+    /// Restricts collocates to a part of speech by searching e.g. `*_NOUN`.
+    pub pos: Option<PartOfSpeech>,
+    /// Maximum number of collocates, the most frequent co-occurrences first,
+    /// for which marginal frequencies are looked up.
+    pub max_collocates: usize,
+    /// Options of the wildcard search. It is always case-sensitive, like the
+    /// lookups of the marginal frequencies.
+    pub search: SearchOptions,
+}
+
+impl Default for CollocationOptions {
+    fn default() -> Self {
+        Self {
+            offset: 1,
+            pos: None,
+            max_collocates: 50,
+            search: SearchOptions::default(),
+        }
+    }
+}
+
+/// Association measure to rank collocates by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Association {
+    Pmi,
+    TScore,
+    LogDice,
+    LogLikelihood,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collocate {
+    pub text: String,
+    /// Matches of the node with this collocate at the requested offset.
+    pub cooccurrences: u64,
+    /// Matches of the collocate as a unigram.
+    pub frequency: u64,
+    /// Pointwise mutual information `log2(O / E)`.
+    pub pmi: f64,
+    /// `(O - E) / sqrt(O)`.
+    pub t_score: f64,
+    /// `14 + log2(2 O / (f(node) + f(collocate)))`.
+    pub log_dice: f64,
+    /// G² of the 2x2 contingency table of node and collocate.
+    pub log_likelihood: f64,
+}
+
+impl Collocate {
+    pub fn score(&self, association: Association) -> f64 {
+        match association {
+            Association::Pmi => self.pmi,
+            Association::TScore => self.t_score,
+            Association::LogDice => self.log_dice,
+            Association::LogLikelihood => self.log_likelihood,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collocations {
+    pub node: String,
+    /// Matches of the node as a unigram.
+    pub node_frequency: u64,
+    /// Total matches of all unigrams in the corpus.
+    pub corpus_size: u64,
+    /// Sorted by log-likelihood, highest first.
+    pub collocates: Vec<Collocate>,
+}
+
+impl Collocations {
+    /// Sorts the collocates by `association`, highest first.
+    pub fn sort_by(&mut self, association: Association) {
+        self.collocates
+            .sort_by(|a, b| b.score(association).total_cmp(&a.score(association)));
+    }
+}
+
+impl Client {
+    /// Collects the words at `options.offset` from `node` and ranks them by
+    /// association with the node. The expected co-occurrence frequency is
+    /// `f(node) f(collocate) / N`, where `N` is the number of unigram matches
+    /// in `totals`. Panics if `options.offset` is out of range.
+    pub async fn collocations(
+        &self,
+        node: &str,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: &CollocationOptions,
+    ) -> Result<Collocations, Error> {
+        let distance = options.offset.unsigned_abs() as usize;
+        assert!(
+            (1..=4).contains(&distance),
+            "offset must be from -4 to 4 except 0"
+        );
+        let collocate = Tagged::new("*", options.pos).to_string();
+        let mut tokens = vec!["*"; distance + 1];
+        let (node_index, collocate_index) = if options.offset > 0 {
+            (0, distance)
+        } else {
+            (distance, 0)
+        };
+        tokens[node_index] = node;
+        tokens[collocate_index] = &collocate;
+        let query = tokens.join(" ");
+
+        let mut cooccurrences: HashMap<String, u64> = HashMap::new();
+        let search = SearchOptions {
+            case_sensitive: true,
+            ..options.search
+        };
+        let mut pages = self.search(query, corpus, search);
+        while let Some(page) = pages.next().await {
+            for ngram in page?.ngrams {
+                let Some(token) = ngram.tokens.get(collocate_index) else {
+                    continue;
+                };
+                if matches!(
+                    token.kind,
+                    NgramTokenKind::SentenceStart | NgramTokenKind::SentenceEnd
+                ) {
+                    continue;
+                }
+                *cooccurrences.entry(token.text.to_string()).or_default() +=
+                    ngram.abs_total_match_count;
+            }
+        }
+        let mut cooccurrences: Vec<_> = cooccurrences.into_iter().collect();
+        cooccurrences.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        cooccurrences.truncate(options.max_collocates);
+
+        let corpus_size = totals
+            .counts(NgramLength::MIN)
+            .map(|(_, count)| count)
+            .sum();
+        let node_frequency = self.unigram_frequency(node, None, corpus).await?;
+        let mut collocates = Vec::with_capacity(cooccurrences.len());
+        for (text, observed) in cooccurrences {
+            let frequency = self.unigram_frequency(&text, options.pos, corpus).await?;
+            collocates.push(associate(
+                text,
+                observed,
+                node_frequency,
+                frequency,
+                corpus_size,
+            ));
+        }
+        let mut collocations = Collocations {
+            node: node.to_string(),
+            node_frequency,
+            corpus_size,
+            collocates,
+        };
+        collocations.sort_by(Association::LogLikelihood);
+        Ok(collocations)
+    }
+
+    /// Total matches of `word`, optionally tagged as `pos`, or zero if it does
+    /// not exist. Collocates like `(` or `/` are searched literally.
+    async fn unigram_frequency(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        corpus: Corpus,
+    ) -> Result<u64, Error> {
+        let options = SearchOptions {
+            max_page_count: 1,
+            case_sensitive: true,
+            dont_interpret_query_operators: has_operators(word),
+            ..Default::default()
+        };
+        let query = Tagged::new(word, pos).to_string();
+        let mut pages = self.search(query, corpus, options);
+        Ok(match pages.next().await.transpose()? {
+            Some(page) => page
+                .ngrams
+                .iter()
+                .find(|ngram| ngram.tokens.len() == 1 && ngram.tokens[0].text == word)
+                .map_or(0, |ngram| ngram.abs_total_match_count),
+            None => 0,
+        })
+    }
+}
+
+fn associate(text: String, observed: u64, node: u64, frequency: u64, n: u64) -> Collocate {
+    // Marginals smaller than the co-occurrences only happen with inconsistent
+    // data, e.g. a collocate that was not found as a unigram.
+    let node = node.max(observed);
+    let frequency = frequency.max(observed);
+    let n = n.max(node + frequency - observed);
+    let o = observed as f64;
+    let expected = node as f64 * frequency as f64 / n as f64;
+    let log_likelihood = Comparison::new(
+        Sample::new(observed, node),
+        Sample::new(frequency - observed, n - node),
+        0.95,
+    )
+    .log_likelihood;
+    Collocate {
+        text,
+        cooccurrences: observed,
+        frequency,
+        pmi: (o / expected).log2(),
+        t_score: (o - expected) / o.sqrt(),
+        log_dice: 14.0 + (2.0 * o / (node + frequency) as f64).log2(),
+        log_likelihood,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{associate, Association, CollocationOptions};
+    use crate::middleware::{Middleware, Request, Response};
+    use crate::testing::{page, total_counts, Fixtures};
+    use crate::{Client, Corpus};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the query and flags of every search.
+    struct Searches(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Middleware for Searches {
+        fn before_request(&self, request: &mut Request) -> Option<Response> {
+            let param = |name: &str| {
+                request
+                    .query
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            };
+            let entry = (param("query"), param("flags"));
+            self.0.lock().unwrap().push(entry);
+            None
+        }
+    }
+
+    #[test]
+    fn association_measures() {
+        let collocate = associate("tea".into(), 10, 100, 200, 10_000);
+        assert!((collocate.pmi - 5f64.log2()).abs() < 1e-12);
+        assert!((collocate.t_score - 8.0 / 10f64.sqrt()).abs() < 1e-12);
+        assert!((collocate.log_dice - (14.0 + (20.0f64 / 300.0).log2())).abs() < 1e-12);
+        assert!(collocate.log_likelihood > 0.0);
+    }
+
+    #[tokio::test]
+    async fn collocates_right_of_node() {
+        let fixtures = Fixtures::default()
+            .search(
+                "strong * *",
+                &page(&[
+                    (&["strong", "black", "tea"], 8),
+                    (&["strong", "green", "tea"], 2),
+                    (&["strong", "and", "_END_"], 5),
+                    (&["strong", "the", "man"], 4),
+                ]),
+            )
+            .search("strong", &page(&[(&["strong"], 100)]))
+            .search("tea", &page(&[(&["tea"], 50), (&["teas"], 10)]))
+            .search("man", &page(&[(&["man"], 1000)]));
+        let client = Client::with_transport(fixtures);
+        let totals = total_counts(1900, &[10_000]);
+        let options = CollocationOptions {
+            offset: 2,
+            ..Default::default()
+        };
+        let mut collocations = client
+            .collocations("strong", Corpus::English, &totals, &options)
+            .await
+            .unwrap();
+        assert_eq!(collocations.node_frequency, 100);
+        assert_eq!(collocations.corpus_size, 10_000);
+        let texts: Vec<_> = collocations
+            .collocates
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(texts, ["tea", "man"]);
+        assert_eq!(collocations.collocates[0].cooccurrences, 10);
+        assert_eq!(collocations.collocates[0].frequency, 50);
+
+        collocations.sort_by(Association::TScore);
+        assert!(collocations.collocates[0].t_score > collocations.collocates[1].t_score);
+    }
+
+    #[tokio::test]
+    async fn case_sensitive_and_literal_lookups() {
+        let fixtures = Fixtures::default()
+            .search(
+                "strong *",
+                &page(&[(&["strong", "tea"], 8), (&["strong", "("], 3)]),
+            )
+            .search("strong", &page(&[(&["strong"], 100)]))
+            .search("tea", &page(&[(&["tea"], 50)]))
+            .search("(", &page(&[(&["("], 500)]));
+        let searches = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::with_transport(fixtures).with_middleware(Searches(searches.clone()));
+        let totals = total_counts(1900, &[10_000]);
+        let collocations = client
+            .collocations(
+                "strong",
+                Corpus::English,
+                &totals,
+                &CollocationOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(collocations.collocates.len(), 2);
+        let parenthesis = collocations
+            .collocates
+            .iter()
+            .find(|c| c.text == "(")
+            .unwrap();
+        assert_eq!(parenthesis.frequency, 500);
+
+        let searches = searches.lock().unwrap();
+        assert!(searches.iter().all(|(_, flags)| flags.contains("cs")));
+        for (query, flags) in searches.iter() {
+            assert_eq!(flags.contains("ri"), query == "(", "{query}");
+        }
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Comparison of alternative phrasings, e.g. `in the weekend` and
+//! `on the weekend`.
+//!
+//! All candidates are looked up with a single alternation query like
+//! `(in the weekend)/(on the weekend)`. Candidates that contain query
+//! operators are searched on their own, as are all candidates if the server
+//! rejects the alternation.
+
+use crate::rarity::{span_text, tokenize};
+use crate::{
+    Client, Corpus, Error, ErrorKind, Measure, Ngram, NgramLite, SearchOptions, TimeSeries,
+    YearRange,
+};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    /// The phrase as given, with whitespace collapsed.
+    pub phrase: String,
+    /// `None` if the phrase is not attested in the corpus.
+    pub ngram: Option<Ngram>,
+    /// Match counts over the years of the [`Alternatives`], zeros if the
+    /// phrase is not attested.
+    pub series: TimeSeries,
+    /// Share of the match counts of all candidates per year.
+    pub ratio: TimeSeries,
+    /// Share of the total match count of all candidates.
+    pub share: f64,
+}
+
+impl Candidate {
+    pub fn is_attested(&self) -> bool {
+        self.ngram.is_some()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.ngram
+            .as_ref()
+            .map_or(0, |ngram| ngram.abs_total_match_count)
+    }
+}
+
+/// Candidates ranked by their total match count, most frequent first and
+/// unattested candidates last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Alternatives {
+    /// From the first to the last year any candidate occurs in, `None` if no
+    /// candidate is attested.
+    pub years: Option<YearRange>,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Alternatives {
+    pub fn get(&self, phrase: &str) -> Option<&Candidate> {
+        let phrase = normalize(phrase);
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.phrase == phrase)
+    }
+
+    /// Match counts of `a` divided by those of `b`, zero in years without
+    /// matches of `b`. Returns `None` if one of the phrases is not a
+    /// candidate.
+    pub fn ratio(&self, a: &str, b: &str) -> Option<TimeSeries> {
+        Some(&self.get(a)?.series / &self.get(b)?.series)
+    }
+
+    /// First year of the run of years up to the last one in which `phrase` was
+    /// more frequent than every other candidate, or `None` if it is not the
+    /// most frequent candidate in the last year.
+    pub fn leading_since(&self, phrase: &str) -> Option<u16> {
+        let leader = self.get(phrase)?;
+        let mut since = None;
+        for year in self.years?.iter().rev() {
+            let value = leader.series.get(year).unwrap_or_default();
+            let leads = self
+                .candidates
+                .iter()
+                .filter(|other| other.phrase != leader.phrase)
+                .all(|other| other.series.get(year).unwrap_or_default() < value);
+            if !leads {
+                break;
+            }
+            since = Some(year);
+        }
+        since
+    }
+}
+
+impl Client {
+    /// Looks up every phrase and compares their frequencies over time.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ngrams::Client) -> Result<(), ngrams::Error> {
+    /// use ngrams::Corpus;
+    ///
+    /// let alternatives = client
+    ///     .compare(Corpus::English, ["in the weekend", "on the weekend"])
+    ///     .await?;
+    /// let since = alternatives.leading_since("on the weekend");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn compare<I, S>(&self, corpus: Corpus, phrases: I) -> Result<Alternatives, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // The server splits contractions and hyphenated words into several
+        // tokens, so phrases are looked up by the texts of their tokens.
+        let mut seen = HashSet::new();
+        let (phrases, keys): (Vec<String>, Vec<String>) = phrases
+            .into_iter()
+            .map(|phrase| {
+                let phrase = normalize(phrase.as_ref());
+                let key = span_text(&tokenize(&phrase, corpus));
+                (phrase, key)
+            })
+            .filter(|(_, key)| !key.is_empty() && seen.insert(key.clone()))
+            .unzip();
+
+        let found = self.find_phrases(corpus, &keys, false).await?;
+        let mut ngrams = Vec::with_capacity(phrases.len());
+        for key in &keys {
+            ngrams.push(match found.get(key) {
+                Some(lite) => self.get_ngram(corpus, &lite.id).await?,
+                None => None,
+            });
+        }
+
+        let years = ngrams
+            .iter()
+            .flatten()
+            .filter_map(|ngram| ngram.time_series(Measure::Absolute).years())
+            .reduce(|a, b| a.span(b));
+        let series: Vec<_> = ngrams
+            .iter()
+            .map(|ngram| match (ngram, years) {
+                (Some(ngram), Some(years)) => ngram.time_series(Measure::Absolute).reindex(years),
+                (_, Some(years)) => TimeSeries::zeros(years),
+                _ => TimeSeries::default(),
+            })
+            .collect();
+        let sum = series.iter().fold(
+            years.map(TimeSeries::zeros).unwrap_or_default(),
+            |sum, s| &sum + s,
+        );
+        let total: u64 = ngrams
+            .iter()
+            .flatten()
+            .map(|ngram| ngram.abs_total_match_count)
+            .sum();
+
+        let mut candidates: Vec<_> = phrases
+            .into_iter()
+            .zip(ngrams)
+            .zip(series)
+            .map(|((phrase, ngram), series)| {
+                let count = ngram
+                    .as_ref()
+                    .map_or(0, |ngram| ngram.abs_total_match_count);
+                Candidate {
+                    phrase,
+                    ngram,
+                    ratio: &series / &sum,
+                    series,
+                    share: if total == 0 {
+                        0.0
+                    } else {
+                        count as f64 / total as f64
+                    },
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.is_attested()
+                .cmp(&a.is_attested())
+                .then_with(|| b.total().cmp(&a.total()))
+        });
+        Ok(Alternatives { years, candidates })
+    }
+
+    /// Maps phrases to the ngrams found for them, searching all phrases with
+    /// one alternation query if possible. Phrases containing query operators
+    /// are searched on their own, and without interpreting the operators if
+    /// `literal` is set.
+    pub(crate) async fn find_phrases(
+        &self,
+        corpus: Corpus,
+        phrases: &[String],
+        literal: bool,
+    ) -> Result<HashMap<String, NgramLite>, Error> {
+        let mut found = HashMap::new();
+        let (plain, special): (Vec<_>, Vec<_>) =
+            phrases.iter().partition(|phrase| !has_operators(phrase));
+        if plain.len() > 1 {
+            match self
+                .search_phrases(corpus, &alternation(&plain), false, &mut found)
+                .await
+            {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::BadInput => {
+                    for phrase in plain {
+                        self.search_phrases(corpus, phrase, false, &mut found)
+                            .await?;
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        } else if let Some(phrase) = plain.first() {
+            self.search_phrases(corpus, phrase, false, &mut found)
+                .await?;
+        }
+        for phrase in special {
+            self.search_phrases(corpus, phrase, literal, &mut found)
+                .await?;
+        }
+        Ok(found)
+    }
+
+    async fn search_phrases(
+        &self,
+        corpus: Corpus,
+        query: &str,
+        literal: bool,
+        found: &mut HashMap<String, NgramLite>,
+    ) -> Result<(), Error> {
+        let options = SearchOptions {
+            case_sensitive: true,
+            exclude_sentence_boundary_tags: true,
+            dont_interpret_query_operators: literal,
+            ..Default::default()
+        };
+        let mut pages = self.search(query, corpus, options);
+        while let Some(page) = pages.next().await {
+            for ngram in page?.ngrams {
+                let texts: Vec<_> = ngram
+                    .tokens
+                    .iter()
+                    .map(|token| token.text.as_ref())
+                    .collect();
+                found
+                    .entry(texts.join(" "))
+                    .or_insert_with(|| ngram.to_ngram_lite());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn normalize(phrase: &str) -> String {
+    phrase.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn has_operators(phrase: &str) -> bool {
+    phrase.contains(['*', '/', '~', '(', ')', '"'])
+}
+
+/// Alternation of `phrases`, grouping multi-word phrases in parentheses.
+pub(crate) fn alternation(phrases: &[&String]) -> String {
+    let alternatives: Vec<_> = phrases
+        .iter()
+        .map(|phrase| {
+            if phrase.contains(' ') {
+                format!("({phrase})")
+            } else {
+                phrase.to_string()
+            }
+        })
+        .collect();
+    alternatives.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::alternation;
+    use crate::testing::Fixtures;
+    use crate::{Client, Corpus, NgramStat, YearRange};
+
+    #[test]
+    fn alternation_groups_multi_word_phrases() {
+        let phrases = ["in the weekend".to_string(), "weekends".to_string()];
+        let phrases: Vec<_> = phrases.iter().collect();
+        assert_eq!(alternation(&phrases), "(in the weekend)/weekends");
+    }
+
+    #[tokio::test]
+    async fn compare_phrases_over_time() {
+        let fixtures = Fixtures::default().found(
+            "(in the weekend)/(on the weekend)/(at the weekend)",
+            &[
+                (
+                    &["in", "the", "weekend"],
+                    vec![NgramStat::new(1900, 8, 0.0), NgramStat::new(1901, 4, 0.0)],
+                ),
+                (
+                    &["on", "the", "weekend"],
+                    vec![NgramStat::new(1901, 6, 0.0), NgramStat::new(1902, 30, 0.0)],
+                ),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+
+        let alternatives = client
+            .compare(
+                Corpus::English,
+                ["in the weekend", "on  the weekend", "at the weekend"],
+            )
+            .await
+            .unwrap();
+        assert_eq!(alternatives.years, Some(YearRange::new(1900, 1902)));
+        let phrases: Vec<_> = alternatives
+            .candidates
+            .iter()
+            .map(|candidate| candidate.phrase.as_str())
+            .collect();
+        assert_eq!(
+            phrases,
+            ["on the weekend", "in the weekend", "at the weekend"]
+        );
+        assert_eq!(alternatives.candidates[0].share, 0.75);
+        let unattested = &alternatives.candidates[2];
+        assert!(!unattested.is_attested());
+        assert_eq!(unattested.series.values(), [0.0; 3]);
+        let in_the = alternatives.get("in the weekend").unwrap();
+        assert_eq!(in_the.ratio.values(), [1.0, 0.4, 0.0]);
+        assert_eq!(
+            alternatives
+                .ratio("on the weekend", "in the weekend")
+                .unwrap()
+                .values(),
+            [0.0, 1.5, 0.0]
+        );
+        assert_eq!(alternatives.leading_since("on the weekend"), Some(1901));
+        assert_eq!(alternatives.leading_since("in the weekend"), None);
+    }
+
+    #[tokio::test]
+    async fn contractions_and_hyphens_are_split_like_the_corpus() {
+        let fixtures = Fixtures::default().found(
+            "(do n't know)/(well - known)",
+            &[
+                (&["do", "n't", "know"], vec![NgramStat::new(1900, 30, 0.0)]),
+                (&["well", "-", "known"], vec![NgramStat::new(1900, 10, 0.0)]),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+
+        let alternatives = client
+            .compare(Corpus::English, ["don't know", "well-known"])
+            .await
+            .unwrap();
+        let dont_know = alternatives.get("don't know").unwrap();
+        assert!(dont_know.is_attested());
+        assert_eq!(dont_know.share, 0.75);
+        assert!(alternatives.get("well-known").unwrap().is_attested());
+    }
+}
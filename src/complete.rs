@@ -0,0 +1,306 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Autocompletion of phrases with prefix queries.
+//!
+//! The last, partially typed word of a phrase is searched as a prefix, e.g.
+//! `hello wor~`. If the phrase ends with whitespace, the next word is
+//! searched as a wildcard instead, e.g. `hello *`.
+//!
+//! Type-ahead UIs issue a request per keystroke, of which only the latest
+//! matters. A [`Typeahead`] takes a ticket whenever it is asked for
+//! completions and abandons a request, without sending any further pages, as
+//! soon as a newer ticket exists. Debouncing is left to the caller's runtime:
+//! wait for a pause in typing with its timer before calling
+//! [`Typeahead::update`], and drop futures that are no longer of interest,
+//! which cancels them.
+
+use crate::text::TextOptions;
+use crate::{Client, Corpus, Error, SearchOptions};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Operator that marks the last query term as a prefix.
+const PREFIX_OPERATOR: char = '~';
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompleteOptions {
+    /// Merges completions that only differ in case. The most frequent variant
+    /// is kept and the counts are summed.
+    pub fold_case: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// Whole phrase as text.
+    pub text: String,
+    /// The completed last word.
+    pub word: String,
+    /// Total match count, including merged case variants.
+    pub count: u64,
+    /// Texts of merged case variants, most frequent first.
+    pub variants: Vec<String>,
+}
+
+impl Client {
+    /// Returns up to `k` completions of `phrase`, most frequent first.
+    pub async fn complete(
+        &self,
+        phrase: &str,
+        corpus: Corpus,
+        k: usize,
+    ) -> Result<Vec<Completion>, Error> {
+        self.complete_with(phrase, corpus, k, CompleteOptions::default())
+            .await
+    }
+
+    pub async fn complete_with(
+        &self,
+        phrase: &str,
+        corpus: Corpus,
+        k: usize,
+        options: CompleteOptions,
+    ) -> Result<Vec<Completion>, Error> {
+        let completions = self
+            .complete_while(phrase, corpus, k, options, || true)
+            .await?;
+        Ok(completions.unwrap_or_default())
+    }
+
+    /// Returns `None` as soon as `is_current` returns false.
+    async fn complete_while<F: Fn() -> bool>(
+        &self,
+        phrase: &str,
+        corpus: Corpus,
+        k: usize,
+        options: CompleteOptions,
+        is_current: F,
+    ) -> Result<Option<Vec<Completion>>, Error> {
+        let Some(query) = completion_query(phrase) else {
+            return Ok(Some(Vec::new()));
+        };
+        let search_options = SearchOptions {
+            exclude_sentence_boundary_tags: true,
+            ..Default::default()
+        };
+        let mut completions: Vec<Completion> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut pages = self.search(query, corpus, search_options);
+        let mut has_k = false;
+        loop {
+            if !is_current() {
+                return Ok(None);
+            }
+            let Some(page) = pages.next().await.transpose()? else {
+                break;
+            };
+            for ngram in page.ngrams {
+                let ngram = ngram.to_ngram_lite();
+                let text = ngram.to_text(&TextOptions::new(corpus));
+                let count = ngram.abs_total_match_count;
+                let key = if options.fold_case {
+                    text.to_lowercase()
+                } else {
+                    text.clone()
+                };
+                match index.get(&key) {
+                    Some(&i) => {
+                        let completion = &mut completions[i];
+                        completion.count += count;
+                        if text != completion.text && !completion.variants.contains(&text) {
+                            completion.variants.push(text);
+                        }
+                    }
+                    None => {
+                        index.insert(key, completions.len());
+                        completions.push(Completion {
+                            text,
+                            word: ngram
+                                .tokens
+                                .last()
+                                .map(|token| token.text.clone())
+                                .unwrap_or_default(),
+                            count,
+                            variants: Vec::new(),
+                        });
+                    }
+                }
+            }
+            // Results arrive most frequent first, so once there are k
+            // distinct completions, later pages can only add to variants.
+            // With case folding, one more page is read for the variants of
+            // the last completions.
+            if completions.len() >= k {
+                if has_k || !options.fold_case {
+                    break;
+                }
+                has_k = true;
+            }
+        }
+        completions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+        completions.truncate(k);
+        Ok(is_current().then_some(completions))
+    }
+}
+
+/// Prefix query for a partially typed phrase, or `None` if there is nothing
+/// to complete.
+fn completion_query(phrase: &str) -> Option<String> {
+    let trimmed = phrase.trim_end();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed.len() < phrase.len() {
+        Some(format!("{trimmed} *"))
+    } else {
+        Some(format!("{trimmed}{PREFIX_OPERATOR}"))
+    }
+}
+
+/// Completions for a text field that are superseded by every new input.
+/// Clones share the same sequence of tickets.
+#[derive(Clone)]
+pub struct Typeahead {
+    client: Client,
+    corpus: Corpus,
+    k: usize,
+    options: CompleteOptions,
+    latest: Arc<AtomicU64>,
+}
+
+impl Typeahead {
+    pub fn new(client: Client, corpus: Corpus, k: usize, options: CompleteOptions) -> Self {
+        Self {
+            client,
+            corpus,
+            k,
+            options,
+            latest: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Takes a ticket immediately and returns completions of `phrase`, or
+    /// `None` if `update` was called again before they were complete.
+    pub fn update(
+        &self,
+        phrase: &str,
+    ) -> impl Future<Output = Result<Option<Vec<Completion>>, Error>> + '_ {
+        let ticket = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        let phrase = phrase.to_string();
+        async move {
+            let is_current = || self.latest.load(Ordering::SeqCst) == ticket;
+            self.client
+                .complete_while(&phrase, self.corpus, self.k, self.options, is_current)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completion_query, CompleteOptions, Typeahead};
+    use crate::middleware::{Middleware, Request, Response};
+    use crate::testing::{ngram_lite, Fixtures};
+    use crate::{Client, Corpus, Page};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Answers every search with the same page and a next page token.
+    struct Endless(Arc<AtomicUsize>);
+
+    impl Middleware for Endless {
+        fn before_request(&self, _request: &mut Request) -> Option<Response> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let mut ngram = ngram_lite(&["hello", "world"], 10);
+            ngram.tokens[1].completed = true;
+            let page = Page {
+                query_tokens: Vec::new(),
+                ngrams: vec![ngram],
+            };
+            let mut body = serde_json::to_value(&page).unwrap();
+            body["nextPageToken"] = "next".into();
+            Some(Response::new(200, body.to_string()))
+        }
+    }
+
+    fn client() -> Client {
+        let mut ngrams: Vec<_> = [
+            (["hello", "world"], 10),
+            (["hello", "work"], 5),
+            (["hello", "World"], 7),
+        ]
+        .iter()
+        .map(|(words, count)| ngram_lite(words, *count))
+        .collect();
+        for ngram in &mut ngrams {
+            ngram.tokens[1].completed = true;
+        }
+        let page = Page {
+            query_tokens: Vec::new(),
+            ngrams,
+        };
+        Client::with_transport(Fixtures::default().search("hello wor~", &page))
+    }
+
+    #[test]
+    fn prefix_or_next_word_query() {
+        assert_eq!(completion_query("hello wor").unwrap(), "hello wor~");
+        assert_eq!(completion_query("hello ").unwrap(), "hello *");
+        assert_eq!(completion_query("  "), None);
+    }
+
+    #[tokio::test]
+    async fn complete_with_case_folding() {
+        let client = client();
+        let completions = client
+            .complete("hello wor", Corpus::English, 2)
+            .await
+            .unwrap();
+        let texts: Vec<_> = completions.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["hello world", "hello World"]);
+        assert_eq!(completions[0].word, "world");
+
+        let options = CompleteOptions { fold_case: true };
+        let completions = client
+            .complete_with("hello wor", Corpus::English, 2, options)
+            .await
+            .unwrap();
+        assert_eq!(completions[0].count, 17);
+        assert_eq!(completions[0].variants, ["hello World"]);
+        assert_eq!(completions[1].text, "hello work");
+    }
+
+    #[tokio::test]
+    async fn case_folding_stops_after_one_more_page() {
+        let num_pages = Arc::new(AtomicUsize::new(0));
+        let client =
+            Client::with_transport(Fixtures::default()).with_middleware(Endless(num_pages.clone()));
+        let options = CompleteOptions { fold_case: true };
+        let completions = client
+            .complete_with("hello wor", Corpus::English, 1, options)
+            .await
+            .unwrap();
+        assert_eq!(completions[0].count, 20);
+        assert!(completions[0].variants.is_empty());
+        assert_eq!(num_pages.load(Ordering::SeqCst), 2);
+
+        let completions = client
+            .complete("hello wor", Corpus::English, 2)
+            .await
+            .unwrap();
+        assert_eq!(completions[0].count, 100);
+        assert!(completions[0].variants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn newer_input_supersedes_older() {
+        let typeahead = Typeahead::new(client(), Corpus::English, 3, Default::default());
+        let stale = typeahead.update("hello w");
+        let latest = typeahead.update("hello wor");
+        assert_eq!(stale.await.unwrap(), None);
+        assert_eq!(latest.await.unwrap().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Estimation of when a text was written from the yearly frequencies of its
+//! ngrams, and detection of anachronistic phrasing.
+//!
+//! Each ngram of the text that is found in the corpus is treated as an
+//! independent draw from the ngrams of the same length published in a given
+//! year. The log-likelihood of a year is the sum of the logarithms of the
+//! ngrams' smoothed relative frequencies in that year. The likelihoods are
+//! normalized to a distribution over all years with books of every length
+//! involved. Ngrams that are not found at all carry no evidence for any
+//! particular year and are ignored.
+
+use crate::analytics::{Attestation, Trend};
+use crate::rarity::{span_text, spans, tokenize, DEFAULT_BATCH_SIZE};
+use crate::{Client, Corpus, Error, Ngram, NgramLength, TimeSeries, TotalCounts, YearRange};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DatingOptions {
+    /// Longest ngrams of the text to consider. Longer ngrams are more specific
+    /// but less often attested.
+    pub max_len: NgramLength,
+    /// Added to the match count of every ngram in every year, so that a single
+    /// ngram missing in a year does not rule the year out.
+    pub smoothing: f64,
+}
+
+impl Default for DatingOptions {
+    fn default() -> Self {
+        Self {
+            max_len: NgramLength::new(3).unwrap(),
+            smoothing: 0.5,
+        }
+    }
+}
+
+/// Ngram of the text together with its trend in the corpus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evidence {
+    /// Tokens of the ngram separated by a space.
+    pub text: String,
+    pub ngram: Ngram,
+    pub trend: Trend,
+}
+
+/// Ngram that was not yet attested in a year.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anachronism<'a> {
+    pub evidence: &'a Evidence,
+    pub first_attestation: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dating {
+    pub evidence: Vec<Evidence>,
+    /// Probability of each year, summing to one. Empty if there is no
+    /// evidence.
+    pub distribution: TimeSeries,
+}
+
+impl Dating {
+    /// Year of the highest probability. Ties go to the earliest year.
+    pub fn most_likely_year(&self) -> Option<u16> {
+        self.distribution.peak().map(|(year, _)| year)
+    }
+
+    /// Shortest run of years around the most likely year whose probabilities
+    /// sum to at least `confidence`, grown towards the more likely neighbor.
+    pub fn period(&self, confidence: f64) -> Option<YearRange> {
+        let years = self.distribution.years()?;
+        let peak = self.most_likely_year()?;
+        let probability = |year: u16| self.distribution.get(year).unwrap_or_default();
+        let (mut first, mut last) = (peak, peak);
+        let mut mass = probability(peak);
+        while mass < confidence && (first > years.first() || last < years.last()) {
+            let before = (first > years.first()).then(|| probability(first - 1));
+            let after = (last < years.last()).then(|| probability(last + 1));
+            if before.unwrap_or(-1.0) >= after.unwrap_or(-1.0) {
+                first -= 1;
+                mass += probability(first);
+            } else {
+                last += 1;
+                mass += probability(last);
+            }
+        }
+        Some(YearRange::new(first, last))
+    }
+
+    /// Ngrams not attested by `year` according to `threshold`, the most
+    /// recently attested first and those never reaching the threshold last.
+    pub fn anachronisms(&self, year: u16, threshold: Attestation) -> Vec<Anachronism<'_>> {
+        let mut anachronisms: Vec<_> = self
+            .evidence
+            .iter()
+            .map(|evidence| Anachronism {
+                evidence,
+                first_attestation: evidence.trend.first_attestation(threshold),
+            })
+            .filter(|anachronism| {
+                anachronism
+                    .first_attestation
+                    .is_none_or(|first| first > year)
+            })
+            .collect();
+        anachronisms.sort_by(|a, b| match (a.first_attestation, b.first_attestation) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        anachronisms
+    }
+}
+
+impl Client {
+    /// Estimates when `text` was written, see the
+    /// [module documentation](crate::dating).
+    ///
+    /// ```no_run
+    /// # async fn example(client: ngrams::Client) -> Result<(), ngrams::Error> {
+    /// use ngrams::analytics::Attestation;
+    /// use ngrams::dating::DatingOptions;
+    /// use ngrams::Corpus;
+    ///
+    /// let totals = client.get_total_counts(Corpus::English).await?;
+    /// let text = "She sent him a telegram about the motor car.";
+    /// let dating = client
+    ///     .date_text(text, Corpus::English, &totals, &DatingOptions::default())
+    ///     .await?;
+    /// let period = dating.period(0.9);
+    /// let anachronisms = dating.anachronisms(1850, Attestation::default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn date_text(
+        &self,
+        text: &str,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: &DatingOptions,
+    ) -> Result<Dating, Error> {
+        let tokens = tokenize(text, corpus);
+        let mut seen = HashSet::new();
+        let mut texts: Vec<_> = spans(&tokens, options.max_len)
+            .into_iter()
+            .map(|range| span_text(&tokens[range]))
+            .filter(|text| seen.insert(text.clone()))
+            .collect();
+        texts.sort();
+
+        let mut evidence = Vec::new();
+        for batch in texts.chunks(DEFAULT_BATCH_SIZE) {
+            let found = self.find_phrases(corpus, batch, true).await?;
+            for text in batch {
+                let Some(lite) = found.get(text) else {
+                    continue;
+                };
+                let Some(ngram) = self.get_ngram(corpus, &lite.id).await? else {
+                    continue;
+                };
+                if ngram.length().is_none() {
+                    continue;
+                }
+                evidence.push(Evidence {
+                    text: text.clone(),
+                    trend: Trend::new(&ngram, totals),
+                    ngram,
+                });
+            }
+        }
+        let distribution = distribution(&evidence, totals, options.smoothing);
+        Ok(Dating {
+            evidence,
+            distribution,
+        })
+    }
+}
+
+fn distribution(evidence: &[Evidence], totals: &TotalCounts, smoothing: f64) -> TimeSeries {
+    let lens: HashSet<_> = evidence
+        .iter()
+        .map(|evidence| evidence.ngram.expect_len())
+        .collect();
+    let log_likelihoods: Vec<_> = totals
+        .years()
+        .iter()
+        .filter(|&year| {
+            lens.iter()
+                .all(|&len| totals.count(len, year).unwrap_or_default() > 0)
+        })
+        .map(|year| {
+            let log_likelihood = evidence
+                .iter()
+                .map(|evidence| {
+                    let count = evidence.trend.absolute().get(year).unwrap_or_default();
+                    let total = totals
+                        .count(evidence.ngram.expect_len(), year)
+                        .unwrap_or_default();
+                    ((count + smoothing) / (total as f64 + smoothing)).ln()
+                })
+                .sum::<f64>();
+            (year, log_likelihood)
+        })
+        .collect();
+    if evidence.is_empty() || log_likelihoods.is_empty() {
+        return TimeSeries::default();
+    }
+    let first_year = log_likelihoods[0].0;
+    let last_year = log_likelihoods[log_likelihoods.len() - 1].0;
+    let max = log_likelihoods
+        .iter()
+        .map(|&(_, log_likelihood)| log_likelihood)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let likelihoods = TimeSeries::from_points(
+        YearRange::new(first_year, last_year),
+        log_likelihoods
+            .into_iter()
+            .map(|(year, log_likelihood)| (year, (log_likelihood - max).exp())),
+    );
+    let sum = likelihoods.sum();
+    likelihoods / sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DatingOptions;
+    use crate::analytics::Attestation;
+    use crate::testing::{total_counts, Fixtures};
+    use crate::{Client, Corpus, NgramStat, YearRange};
+
+    #[tokio::test]
+    async fn date_text_by_its_ngrams() {
+        let stats = |counts: [u64; 4]| {
+            (1900..)
+                .zip(counts)
+                .map(|(year, count)| NgramStat::new(year, count, 0.0))
+                .collect()
+        };
+        let fixtures = Fixtures::default().found(
+            "engine/steam/(steam engine)",
+            &[
+                (&["steam"], stats([10, 10, 10, 10])),
+                (&["engine"], stats([10, 10, 10, 10])),
+                (&["steam", "engine"], stats([0, 0, 50, 50])),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+        let totals = total_counts(1900, &[100, 100, 100, 100]);
+
+        let dating = client
+            .date_text(
+                "steam engine",
+                Corpus::English,
+                &totals,
+                &DatingOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(dating.evidence.len(), 3);
+        assert_eq!(
+            dating.distribution.years(),
+            Some(YearRange::new(1900, 1903))
+        );
+        assert!((dating.distribution.sum() - 1.0).abs() < 1e-9);
+        assert_eq!(dating.most_likely_year(), Some(1902));
+        assert_eq!(dating.period(0.9), Some(YearRange::new(1902, 1903)));
+
+        let threshold = Attestation {
+            min_match_count: 1,
+            consecutive_years: 1,
+        };
+        let anachronisms = dating.anachronisms(1901, threshold);
+        assert_eq!(anachronisms.len(), 1);
+        assert_eq!(anachronisms[0].evidence.text, "steam engine");
+        assert_eq!(anachronisms[0].first_attestation, Some(1902));
+        assert!(dating.anachronisms(1902, threshold).is_empty());
+    }
+}
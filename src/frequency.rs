@@ -0,0 +1,167 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Relative frequencies over arbitrary year ranges.
+
+use crate::{Ngram, NgramLength, TotalCounts, YearRange};
+use std::{error, fmt};
+
+/// Requested years are not within the years covered by the corpus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct YearRangeError {
+    pub requested: YearRange,
+    pub available: YearRange,
+}
+
+impl fmt::Display for YearRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "year range {} is not within {}",
+            self.requested, self.available
+        )
+    }
+}
+
+impl error::Error for YearRangeError {}
+
+impl TotalCounts {
+    /// Returns the sum of the total match counts of all ngrams of length `len`
+    /// within `years`.
+    pub fn total_in(&self, len: NgramLength, years: YearRange) -> Result<u64, YearRangeError> {
+        self.check_years(years)?;
+        let start = (years.first() - self.min_year) as usize;
+        Ok(self.by_year(len)[start..start + years.num_years()]
+            .iter()
+            .sum())
+    }
+
+    fn check_years(&self, years: YearRange) -> Result<(), YearRangeError> {
+        if self.years().contains_range(years) {
+            Ok(())
+        } else {
+            Err(YearRangeError {
+                requested: years,
+                available: self.years(),
+            })
+        }
+    }
+}
+
+impl Ngram {
+    /// Returns the number of matches within `years` divided by the total
+    /// number of matches of all ngrams of the same length within `years`.
+    ///
+    /// The totals are chosen by the number of tokens of this ngram. Returns
+    /// zero if the corpus has no matches at all in `years`. Panics if the
+    /// ngram does not have 1 to 5 tokens.
+    pub fn relative_frequency(
+        &self,
+        totals: &TotalCounts,
+        years: YearRange,
+    ) -> Result<f64, YearRangeError> {
+        let total = totals.total_in(self.expect_len(), years)?;
+        Ok(ratio(self.match_count_in(years), total))
+    }
+
+    /// Like [`Ngram::relative_frequency`] for many ngrams at once. The totals
+    /// per ngram length are summed only once.
+    pub fn relative_frequencies<'a, I>(
+        ngrams: I,
+        totals: &TotalCounts,
+        years: YearRange,
+    ) -> Result<Vec<f64>, YearRangeError>
+    where
+        I: IntoIterator<Item = &'a Ngram>,
+    {
+        totals.check_years(years)?;
+        let mut sums: [Option<u64>; 5] = [None; 5];
+        ngrams
+            .into_iter()
+            .map(|ngram| {
+                let len = ngram.expect_len();
+                let total = match sums[len.get() - 1] {
+                    Some(total) => total,
+                    None => *sums[len.get() - 1].insert(totals.total_in(len, years)?),
+                };
+                Ok(ratio(ngram.match_count_in(years), total))
+            })
+            .collect()
+    }
+
+    pub(crate) fn expect_len(&self) -> NgramLength {
+        self.length()
+            .unwrap_or_else(|| panic!("invalid ngram length {}", self.tokens.len()))
+    }
+
+    pub(crate) fn match_count_in(&self, years: YearRange) -> u64 {
+        self.stats
+            .iter()
+            .filter(|stat| years.contains(stat.year))
+            .map(|stat| stat.abs_match_count)
+            .sum()
+    }
+}
+
+fn ratio(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YearRangeError;
+    use crate::testing::{ngram, tokens, total_counts};
+    use crate::{Ngram, NgramStat, YearRange};
+
+    fn with_tokens(mut ngram: Ngram, len: usize) -> Ngram {
+        ngram.tokens = tokens(&vec!["x"; len]);
+        ngram
+    }
+
+    #[test]
+    fn relative_frequency_within_year_range() {
+        let totals = total_counts(1900, &[100, 100, 200, 100]);
+        let ngram = with_tokens(
+            ngram(vec![
+                NgramStat::new(1900, 10, 0.1),
+                NgramStat::new(1902, 20, 0.1),
+                NgramStat::new(1903, 50, 0.5),
+            ]),
+            2,
+        );
+        let years = YearRange::new(1901, 1902);
+        assert_eq!(ngram.relative_frequency(&totals, years), Ok(20.0 / 300.0));
+        assert_eq!(
+            ngram.relative_frequency(&totals, YearRange::new(1900, 1903)),
+            Ok(80.0 / 500.0)
+        );
+        assert_eq!(
+            ngram.relative_frequency(&totals, YearRange::new(2000, 2001)),
+            Ok(0.0)
+        );
+
+        let other = with_tokens(ngram.clone(), 1);
+        let batch = Ngram::relative_frequencies([&ngram, &other], &totals, years).unwrap();
+        assert_eq!(batch, [20.0 / 300.0, 20.0 / 300.0]);
+    }
+
+    #[test]
+    fn year_range_outside_corpus_is_an_error() {
+        let totals = total_counts(1900, &[]);
+        let ngram = with_tokens(ngram(Vec::new()), 1);
+        let years = YearRange::new(1899, 1950);
+        assert_eq!(
+            ngram.relative_frequency(&totals, years),
+            Err(YearRangeError {
+                requested: years,
+                available: totals.years(),
+            })
+        );
+        assert!(Ngram::relative_frequencies([&ngram], &totals, years).is_err());
+    }
+}
@@ -0,0 +1,524 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Expansion of German and Russian lemmas into their inflected forms.
+//!
+//! A query for a single form, like Russian `книга`, misses most occurrences
+//! of the lemma in a heavily inflected language. The forms are generated from
+//! the ending of the lemma with bundled paradigm tables, plus tables of common
+//! irregular verbs. Like the [`orthography`](crate::orthography) rules, the
+//! paradigms overgenerate: forms that do not exist are simply not found, but
+//! forms shared with other lemmas are counted too.
+
+use crate::compare::alternation;
+use crate::variants::VariantGroup;
+use crate::{Client, Corpus, Error, PartOfSpeech};
+use std::collections::HashMap;
+
+/// Number of alternatives per query, a conservative bound to stay clear of
+/// the server's limits on query complexity.
+pub const MAX_ALTERNATIVES: usize = 16;
+
+const GERMAN_IRREGULAR_VERBS: &[(&str, &[&str])] = &[
+    (
+        "sein",
+        &[
+            "bin", "bist", "ist", "sind", "seid", "war", "warst", "waren", "wart", "gewesen",
+            "sei", "seiest", "wäre", "wären", "seiend",
+        ],
+    ),
+    (
+        "haben",
+        &[
+            "habe", "hast", "hat", "habt", "hatte", "hattest", "hatten", "hattet", "gehabt",
+            "hätte", "hätten",
+        ],
+    ),
+    (
+        "werden",
+        &[
+            "werde", "wirst", "wird", "werdet", "wurde", "wurdest", "wurden", "wurdet", "geworden",
+            "worden", "würde", "würden",
+        ],
+    ),
+    (
+        "gehen",
+        &[
+            "gehe", "gehst", "geht", "ging", "gingst", "gingen", "gingt", "gegangen",
+        ],
+    ),
+    (
+        "kommen",
+        &[
+            "komme", "kommst", "kommt", "kam", "kamst", "kamen", "kamt", "gekommen", "käme",
+        ],
+    ),
+    (
+        "sehen",
+        &[
+            "sehe", "siehst", "sieht", "seht", "sah", "sahst", "sahen", "saht", "gesehen",
+        ],
+    ),
+    (
+        "geben",
+        &[
+            "gebe", "gibst", "gibt", "gebt", "gab", "gabst", "gaben", "gabt", "gegeben",
+        ],
+    ),
+    (
+        "können",
+        &[
+            "kann", "kannst", "könnt", "konnte", "konntest", "konnten", "konntet", "gekonnt",
+            "könnte", "könnten",
+        ],
+    ),
+];
+
+const RUSSIAN_IRREGULAR_VERBS: &[(&str, &[&str])] = &[
+    (
+        "быть",
+        &[
+            "есть",
+            "был",
+            "была",
+            "было",
+            "были",
+            "буду",
+            "будешь",
+            "будет",
+            "будем",
+            "будете",
+            "будут",
+            "будь",
+            "будьте",
+        ],
+    ),
+    (
+        "хотеть",
+        &[
+            "хочу",
+            "хочешь",
+            "хочет",
+            "хотим",
+            "хотите",
+            "хотят",
+            "хотел",
+            "хотела",
+            "хотело",
+            "хотели",
+        ],
+    ),
+    (
+        "мочь",
+        &[
+            "могу",
+            "можешь",
+            "может",
+            "можем",
+            "можете",
+            "могут",
+            "мог",
+            "могла",
+            "могло",
+            "могли",
+        ],
+    ),
+    (
+        "идти",
+        &[
+            "иду",
+            "идёшь",
+            "идет",
+            "идёт",
+            "идём",
+            "идёте",
+            "идут",
+            "шёл",
+            "шел",
+            "шла",
+            "шло",
+            "шли",
+            "иди",
+            "идите",
+        ],
+    ),
+];
+
+/// `lemma` followed by its inflected forms. Parts of speech other than nouns,
+/// adjectives and verbs, and the English corpus, yield the lemma only.
+pub fn inflect(lemma: &str, pos: PartOfSpeech, corpus: Corpus) -> Vec<String> {
+    let mut forms = vec![lemma.to_string()];
+    let inflected = match (corpus, pos) {
+        (Corpus::German, PartOfSpeech::Noun) => german_noun(lemma),
+        (Corpus::German, PartOfSpeech::Adj) => german_adjective(lemma),
+        (Corpus::German, PartOfSpeech::Verb) => german_verb(lemma),
+        (Corpus::Russian, PartOfSpeech::Noun) => russian_noun(lemma),
+        (Corpus::Russian, PartOfSpeech::Adj) => russian_adjective(lemma),
+        (Corpus::Russian, PartOfSpeech::Verb) => russian_verb(lemma),
+        _ => Vec::new(),
+    };
+    for form in inflected {
+        if !form.is_empty() && !forms.contains(&form) {
+            forms.push(form);
+        }
+    }
+    forms
+}
+
+/// Alternation queries covering `forms`, with up to [`MAX_ALTERNATIVES`]
+/// forms each.
+pub fn queries(forms: &[String]) -> Vec<String> {
+    forms
+        .chunks(MAX_ALTERNATIVES)
+        .map(|chunk| alternation(&chunk.iter().collect::<Vec<_>>()))
+        .collect()
+}
+
+fn suffixed(stem: &str, endings: &[&str]) -> Vec<String> {
+    endings
+        .iter()
+        .map(|ending| format!("{stem}{ending}"))
+        .collect()
+}
+
+/// Umlaut of the last `a`, `o`, `u` or `au` of `stem`.
+fn umlaut(stem: &str) -> Option<String> {
+    let (i, c) = stem
+        .char_indices()
+        .rev()
+        .find(|(_, c)| matches!(c, 'a' | 'o' | 'u' | 'A' | 'O' | 'U'))?;
+    let before = &stem[..i];
+    let after = &stem[i + c.len_utf8()..];
+    if matches!(c, 'u') && (before.ends_with('a') || before.ends_with('A')) {
+        let a = before.chars().last()?;
+        let umlaut = if a == 'a' { 'ä' } else { 'Ä' };
+        return Some(format!("{}{umlaut}u{after}", &before[..before.len() - 1]));
+    }
+    let umlaut = match c {
+        'a' => 'ä',
+        'o' => 'ö',
+        'u' => 'ü',
+        'A' => 'Ä',
+        'O' => 'Ö',
+        _ => 'Ü',
+    };
+    Some(format!("{before}{umlaut}{after}"))
+}
+
+fn german_noun(lemma: &str) -> Vec<String> {
+    let mut forms = Vec::new();
+    if lemma.ends_with('e') {
+        forms.extend(suffixed(lemma, &["n", "s", "ns"]));
+    } else if lemma.ends_with("in") {
+        forms.extend(suffixed(lemma, &["nen"]));
+    } else if ["el", "er", "en"]
+        .iter()
+        .any(|ending| lemma.ends_with(ending))
+    {
+        forms.extend(suffixed(lemma, &["n", "s"]));
+        if let Some(umlaut) = umlaut(lemma) {
+            forms.extend(suffixed(&umlaut, &["", "n"]));
+        }
+    } else {
+        forms.extend(suffixed(lemma, &["e", "es", "s", "en", "er", "ern"]));
+        if let Some(umlaut) = umlaut(lemma) {
+            forms.extend(suffixed(&umlaut, &["e", "en", "er", "ern"]));
+        }
+    }
+    forms
+}
+
+fn german_adjective(lemma: &str) -> Vec<String> {
+    const ENDINGS: [&str; 6] = ["", "e", "en", "em", "er", "es"];
+    let stem = lemma.strip_suffix('e').unwrap_or(lemma);
+    let superlative = if stem.ends_with(['s', 'ß', 't', 'd', 'z']) {
+        "est"
+    } else {
+        "st"
+    };
+    let mut stems = vec![stem.to_string()];
+    stems.extend(umlaut(stem));
+    let mut forms = suffixed(stem, &ENDINGS[1..]);
+    for stem in &stems {
+        forms.extend(suffixed(&format!("{stem}er"), &ENDINGS));
+        forms.extend(suffixed(&format!("{stem}{superlative}"), &ENDINGS));
+    }
+    forms
+}
+
+fn german_verb(lemma: &str) -> Vec<String> {
+    if let Some((_, forms)) = GERMAN_IRREGULAR_VERBS
+        .iter()
+        .find(|(verb, _)| *verb == lemma)
+    {
+        return forms.iter().map(|form| form.to_string()).collect();
+    }
+    let Some(stem) = lemma.strip_suffix("en").or_else(|| lemma.strip_suffix('n')) else {
+        return Vec::new();
+    };
+    let e = if stem.ends_with(['t', 'd']) { "e" } else { "" };
+    let prefix = if lemma.ends_with("ieren") { "" } else { "ge" };
+    let mut forms = suffixed(stem, &["e", "en", "end"]);
+    forms.extend(suffixed(
+        &format!("{stem}{e}"),
+        &["st", "t", "te", "test", "ten", "tet"],
+    ));
+    forms.push(format!("{prefix}{stem}{e}t"));
+    forms
+}
+
+/// Applies the Russian spelling rules for endings after velars and
+/// sibilants, e.g. `книгы` becomes `книги`.
+fn russian_suffixed(stem: &str, endings: &[&str]) -> Vec<String> {
+    let last = stem.chars().last();
+    let velar_or_sibilant = last.is_some_and(|c| "гкхжшчщ".contains(c));
+    let sibilant = last.is_some_and(|c| "жшчщц".contains(c));
+    endings
+        .iter()
+        .map(|ending| {
+            let mut chars = ending.chars();
+            let first = chars.next();
+            let rest = chars.as_str();
+            match first {
+                Some('ы') if velar_or_sibilant => format!("{stem}и{rest}"),
+                Some('ю') if sibilant => format!("{stem}у{rest}"),
+                Some('я') if sibilant => format!("{stem}а{rest}"),
+                _ => format!("{stem}{ending}"),
+            }
+        })
+        .collect()
+}
+
+fn russian_noun(lemma: &str) -> Vec<String> {
+    let strip = |ending: &str| lemma.strip_suffix(ending).filter(|stem| !stem.is_empty());
+    if let Some(stem) = strip("ие") {
+        return russian_suffixed(stem, &["ия", "ию", "ием", "ии", "ий", "иям", "иями", "иях"]);
+    }
+    if let Some(stem) = strip("а") {
+        return russian_suffixed(stem, &["ы", "е", "у", "ой", "ою", "", "ам", "ами", "ах"]);
+    }
+    if let Some(stem) = strip("я") {
+        return russian_suffixed(stem, &["и", "е", "ю", "ей", "ь", "ям", "ями", "ях"]);
+    }
+    if let Some(stem) = strip("о") {
+        return russian_suffixed(stem, &["а", "у", "ом", "е", "", "ам", "ами", "ах"]);
+    }
+    if let Some(stem) = strip("е") {
+        return russian_suffixed(stem, &["я", "ю", "ем", "ей", "ям", "ями", "ях"]);
+    }
+    if let Some(stem) = strip("ь") {
+        // Masculine and feminine nouns in -ь decline differently.
+        return russian_suffixed(
+            stem,
+            &[
+                "я", "ю", "ем", "ём", "е", "и", "ью", "ей", "ям", "ями", "ях",
+            ],
+        );
+    }
+    if let Some(stem) = strip("й") {
+        return russian_suffixed(stem, &["я", "ю", "ем", "е", "и", "ев", "ям", "ями", "ях"]);
+    }
+    let mut forms = russian_suffixed(lemma, &["а", "у", "ом", "е", "ы", "ов", "ам", "ами", "ах"]);
+    if lemma.ends_with(['ж', 'ш', 'ч', 'щ']) {
+        forms.extend(suffixed(lemma, &["ей", "ем"]));
+    }
+    forms
+}
+
+fn russian_adjective(lemma: &str) -> Vec<String> {
+    const HARD: [&str; 12] = [
+        "ый", "ой", "ая", "ое", "ые", "ого", "ому", "ую", "ым", "ом", "ых", "ыми",
+    ];
+    const SOFT: [&str; 12] = [
+        "ий", "яя", "ее", "ие", "его", "ей", "ему", "юю", "им", "ем", "их", "ими",
+    ];
+    let Some(stem) = ["ый", "ой", "ий"]
+        .iter()
+        .find_map(|ending| lemma.strip_suffix(ending))
+    else {
+        return Vec::new();
+    };
+    if lemma.ends_with("ий") && !stem.ends_with(['г', 'к', 'х', 'ж', 'ш', 'ч', 'щ']) {
+        return suffixed(stem, &SOFT);
+    }
+    let mut forms = russian_suffixed(stem, &HARD);
+    if stem.ends_with(['ж', 'ш', 'ч', 'щ']) {
+        // Unstressed endings after sibilants, e.g. хорошее.
+        forms.extend(suffixed(stem, &["ее", "его", "ей", "ему", "ем"]));
+    }
+    forms
+}
+
+fn russian_verb(lemma: &str) -> Vec<String> {
+    if let Some((_, forms)) = RUSSIAN_IRREGULAR_VERBS
+        .iter()
+        .find(|(verb, _)| *verb == lemma)
+    {
+        return forms.iter().map(|form| form.to_string()).collect();
+    }
+    if let Some(base) = lemma
+        .strip_suffix("ся")
+        .or_else(|| lemma.strip_suffix("сь"))
+    {
+        return russian_verb(base)
+            .into_iter()
+            .chain([base.to_string()])
+            .map(|form| {
+                let vowel = form.ends_with(['а', 'е', 'ё', 'и', 'о', 'у', 'ы', 'э', 'ю', 'я']);
+                format!("{form}{}", if vowel { "сь" } else { "ся" })
+            })
+            .collect();
+    }
+    if let Some(stem) = lemma.strip_suffix("ить") {
+        return russian_suffixed(
+            stem,
+            &[
+                "ю", "ишь", "ит", "им", "ите", "ят", "ил", "ила", "ило", "или", "и",
+            ],
+        );
+    }
+    if let Some(stem) = lemma
+        .strip_suffix("ть")
+        .filter(|stem| stem.ends_with(['а', 'я', 'е']))
+    {
+        return suffixed(
+            stem,
+            &[
+                "ю", "ешь", "ет", "ем", "ете", "ют", "л", "ла", "ло", "ли", "й", "йте", "я",
+            ],
+        );
+    }
+    Vec::new()
+}
+
+impl Client {
+    /// Searches all [`inflect`]ed forms of `lemma` and combines their series.
+    /// The members of the group are the forms found, the most frequent first.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ngrams::Client) -> Result<(), ngrams::Error> {
+    /// use ngrams::{Corpus, PartOfSpeech};
+    ///
+    /// let book = client
+    ///     .lemma_group("книга", PartOfSpeech::Noun, Corpus::Russian)
+    ///     .await?;
+    /// println!("{} matches in {} forms", book.total, book.members.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn lemma_group(
+        &self,
+        lemma: &str,
+        pos: PartOfSpeech,
+        corpus: Corpus,
+    ) -> Result<VariantGroup, Error> {
+        let forms = inflect(lemma, pos, corpus);
+        let mut found = HashMap::new();
+        for chunk in forms.chunks(MAX_ALTERNATIVES) {
+            found.extend(self.find_phrases(corpus, chunk, false).await?);
+        }
+        let mut members = Vec::new();
+        for form in &forms {
+            let Some(lite) = found.get(form) else {
+                continue;
+            };
+            if let Some(ngram) = self.get_ngram(corpus, &lite.id).await? {
+                members.push(ngram);
+            }
+        }
+        Ok(VariantGroup::new(lemma.to_string(), members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inflect, queries, MAX_ALTERNATIVES};
+    use crate::testing::Fixtures;
+    use crate::{Client, Corpus, NgramStat, PartOfSpeech};
+
+    fn has_forms(lemma: &str, pos: PartOfSpeech, corpus: Corpus, expected: &[&str]) {
+        let forms = inflect(lemma, pos, corpus);
+        for form in expected {
+            assert!(forms.contains(&form.to_string()), "{lemma}: {form} missing");
+        }
+    }
+
+    #[test]
+    fn german_paradigms() {
+        use PartOfSpeech::{Adj, Noun, Verb};
+        has_forms(
+            "Haus",
+            Noun,
+            Corpus::German,
+            &["Hauses", "Häuser", "Häusern"],
+        );
+        has_forms("Blume", Noun, Corpus::German, &["Blumen"]);
+        has_forms("Apfel", Noun, Corpus::German, &["Äpfel", "Äpfeln"]);
+        has_forms("alt", Adj, Corpus::German, &["alten", "älter", "ältesten"]);
+        has_forms(
+            "machen",
+            Verb,
+            Corpus::German,
+            &["machst", "machte", "gemacht"],
+        );
+        has_forms(
+            "arbeiten",
+            Verb,
+            Corpus::German,
+            &["arbeitet", "gearbeitet"],
+        );
+        has_forms("studieren", Verb, Corpus::German, &["studiert"]);
+        has_forms("sein", Verb, Corpus::German, &["ist", "war", "gewesen"]);
+        assert_eq!(inflect("Haus", PartOfSpeech::Adv, Corpus::German), ["Haus"]);
+    }
+
+    #[test]
+    fn russian_paradigms() {
+        use PartOfSpeech::{Adj, Noun, Verb};
+        has_forms("книга", Noun, Corpus::Russian, &["книги", "книгой", "книг"]);
+        has_forms("стол", Noun, Corpus::Russian, &["столов", "столами"]);
+        has_forms("здание", Noun, Corpus::Russian, &["здания", "зданий"]);
+        has_forms("новый", Adj, Corpus::Russian, &["новая", "новых"]);
+        has_forms("русский", Adj, Corpus::Russian, &["русская", "русских"]);
+        has_forms("синий", Adj, Corpus::Russian, &["синяя", "синего"]);
+        has_forms("читать", Verb, Corpus::Russian, &["читают", "читал"]);
+        has_forms("учить", Verb, Corpus::Russian, &["учу", "учат", "учила"]);
+        has_forms(
+            "заниматься",
+            Verb,
+            Corpus::Russian,
+            &["занимается", "занималась", "занимаюсь"],
+        );
+        has_forms("быть", Verb, Corpus::Russian, &["был", "будут"]);
+    }
+
+    #[test]
+    fn queries_respect_alternative_limit() {
+        let forms = inflect("alt", PartOfSpeech::Adj, Corpus::German);
+        let queries = queries(&forms);
+        assert_eq!(queries.len(), forms.len().div_ceil(MAX_ALTERNATIVES));
+        assert!(queries[0].starts_with("alt/alte/"));
+    }
+
+    #[tokio::test]
+    async fn lemma_group_sums_forms() {
+        let forms = inflect("Haus", PartOfSpeech::Noun, Corpus::German);
+        let fixtures = Fixtures::default().found(
+            &queries(&forms)[0],
+            &[
+                (&["Haus"], vec![NgramStat::new(2000, 7, 0.0)]),
+                (&["Häuser"], vec![NgramStat::new(2001, 3, 0.0)]),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+        let group = client
+            .lemma_group("Haus", PartOfSpeech::Noun, Corpus::German)
+            .await
+            .unwrap();
+        assert_eq!(group.key, "Haus");
+        assert_eq!(group.total, 10);
+        assert_eq!(group.members.len(), 2);
+        assert_eq!(group.series.values(), [7.0, 3.0]);
+    }
+}
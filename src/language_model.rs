@@ -0,0 +1,346 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Ngram language model with Stupid Backoff (Brants et al., 2007) on top of
+//! search results.
+//!
+//! The score of a word `w` after a context `c` is `count(c w) / count(c)` for
+//! the longest suffix of the context, of up to `order - 1` tokens, for which
+//! `c w` is attested, multiplied by the backoff factor for every token dropped
+//! from the context. Without any attested suffix, it is the relative frequency of `w`
+//! among all unigrams, with half a match assumed for unattested words. Scores
+//! are not normalized to probabilities, but rank continuations the same way
+//! and work well for large corpora.
+//!
+//! Counts are fetched with exact searches, continuations with trailing
+//! wildcards like `the quick brown *`, and both are cached by the model.
+
+use crate::compare::has_operators;
+use crate::frequency::YearRangeError;
+use crate::rarity::{tokenize, DEFAULT_BATCH_SIZE};
+use crate::{Client, Corpus, Error, NgramLength, NgramLite, SearchOptions, TotalCounts, YearRange};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LanguageModelOptions {
+    /// Longest ngrams used, i.e. the context has up to `order - 1` tokens.
+    pub order: NgramLength,
+    /// Factor applied per token dropped from the context.
+    pub backoff: f64,
+    /// Counts only matches within these years, which requires fetching the
+    /// yearly stats of every ngram looked up.
+    pub years: Option<YearRange>,
+    /// Number of result pages fetched for the continuations of a context.
+    pub max_page_count: u32,
+}
+
+impl Default for LanguageModelOptions {
+    fn default() -> Self {
+        Self {
+            order: NgramLength::MAX,
+            backoff: 0.4,
+            years: None,
+            max_page_count: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prediction {
+    pub word: String,
+    pub score: f64,
+}
+
+#[derive(Clone)]
+pub struct NgramLanguageModel {
+    client: Client,
+    corpus: Corpus,
+    options: LanguageModelOptions,
+    unigram_total: u64,
+    counts: HashMap<String, u64>,
+    continuations: HashMap<String, Vec<(String, u64)>>,
+}
+
+impl NgramLanguageModel {
+    /// Fails if `options.years` is not within the years of `totals`.
+    pub fn new(
+        client: Client,
+        corpus: Corpus,
+        totals: &TotalCounts,
+        options: LanguageModelOptions,
+    ) -> Result<Self, YearRangeError> {
+        let years = options.years.unwrap_or(totals.years());
+        let unigram_total = totals.total_in(NgramLength::MIN, years)?;
+        Ok(Self {
+            client,
+            corpus,
+            options,
+            unigram_total,
+            counts: HashMap::new(),
+            continuations: HashMap::new(),
+        })
+    }
+
+    /// Stupid Backoff score of `word` following `context`.
+    pub async fn probability(&mut self, context: &str, word: &str) -> Result<f64, Error> {
+        let mut tokens = self.context(context);
+        tokens.push(word.to_string());
+        self.prefetch(&tokens).await?;
+        Ok(self.score_last(&tokens))
+    }
+
+    /// Up to `k` best scoring next words after `context`. Continuations are
+    /// only searched for non-empty suffixes of the context, since the server
+    /// rejects a bare `*`, so an empty context predicts nothing.
+    pub async fn predict(&mut self, context: &str, k: usize) -> Result<Vec<Prediction>, Error> {
+        let context = self.context(context);
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        for start in 0..context.len() {
+            let suffix = &context[start..];
+            let dropped = self.options.backoff.powi(start as i32);
+            let denominator = self.count(&suffix.join(" ")).await?;
+            if denominator == 0 {
+                continue;
+            }
+            for (word, count) in self.continuations(suffix).await? {
+                scores
+                    .entry(word)
+                    .or_insert(dropped * count as f64 / denominator as f64);
+            }
+            if scores.len() >= k {
+                break;
+            }
+        }
+        let mut predictions: Vec<_> = scores
+            .into_iter()
+            .map(|(word, score)| Prediction { word, score })
+            .collect();
+        predictions.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        predictions.truncate(k);
+        Ok(predictions)
+    }
+
+    /// Sum of the decimal logarithms of the scores of all tokens of
+    /// `sentence`, each given the tokens before it.
+    pub async fn score(&mut self, sentence: &str) -> Result<f64, Error> {
+        let tokens: Vec<_> = tokenize(sentence, self.corpus)
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+        self.prefetch(&tokens).await?;
+        let order = self.options.order.get();
+        Ok((0..tokens.len())
+            .map(|i| {
+                let start = (i + 1).saturating_sub(order);
+                self.score_last(&tokens[start..=i]).log10()
+            })
+            .sum())
+    }
+
+    /// Number of cached counts and continuations.
+    pub fn cache_len(&self) -> usize {
+        self.counts.len() + self.continuations.len()
+    }
+
+    /// Last `order - 1` tokens of `context`.
+    fn context(&self, context: &str) -> Vec<String> {
+        let tokens = tokenize(context, self.corpus);
+        let start = tokens.len().saturating_sub(self.options.order.get() - 1);
+        tokens[start..]
+            .iter()
+            .map(|token| token.text.clone())
+            .collect()
+    }
+
+    /// Score of the last token given the ones before it. All counts must have
+    /// been fetched.
+    fn score_last(&self, tokens: &[String]) -> f64 {
+        let (word, context) = tokens.split_last().unwrap();
+        for start in 0..context.len() {
+            let count = self.counts[&tokens[start..].join(" ")];
+            if count > 0 {
+                let denominator = self.counts[&context[start..].join(" ")];
+                let dropped = self.options.backoff.powi(start as i32);
+                return dropped * count as f64 / denominator.max(count) as f64;
+            }
+        }
+        let dropped = self.options.backoff.powi(context.len() as i32);
+        dropped * (self.counts[word] as f64 + 0.5) / (self.unigram_total as f64 + 0.5)
+    }
+
+    /// Fetches the counts of all subsequences of `tokens` of up to `order`
+    /// tokens.
+    async fn prefetch(&mut self, tokens: &[String]) -> Result<(), Error> {
+        let mut seen = HashSet::new();
+        let mut missing = Vec::new();
+        let mut lookups = 0;
+        for end in 1..=tokens.len() {
+            for start in end.saturating_sub(self.options.order.get())..end {
+                let phrase = tokens[start..end].join(" ");
+                lookups += 1;
+                if !self.counts.contains_key(&phrase) && seen.insert(phrase.clone()) {
+                    missing.push(phrase);
+                }
+            }
+        }
+        missing.sort();
+        self.client
+            .record_cache(lookups - missing.len(), missing.len());
+        for batch in missing.chunks(DEFAULT_BATCH_SIZE) {
+            let found = self.client.find_phrases(self.corpus, batch, true).await?;
+            for phrase in batch {
+                let count = match found.get(phrase) {
+                    Some(ngram) => self.count_in_years(ngram).await?,
+                    None => 0,
+                };
+                self.counts.insert(phrase.clone(), count);
+            }
+        }
+        Ok(())
+    }
+
+    async fn count(&mut self, phrase: &str) -> Result<u64, Error> {
+        if let Some(&count) = self.counts.get(phrase) {
+            self.client.record_cache(1, 0);
+            return Ok(count);
+        }
+        self.prefetch(&[phrase.to_string()]).await?;
+        Ok(self.counts[phrase])
+    }
+
+    async fn continuations(&mut self, context: &[String]) -> Result<Vec<(String, u64)>, Error> {
+        let context = context.join(" ");
+        if let Some(continuations) = self.continuations.get(&context) {
+            self.client.record_cache(1, 0);
+            return Ok(continuations.clone());
+        }
+        self.client.record_cache(0, 1);
+        let mut continuations = Vec::new();
+        if !has_operators(&context) {
+            let options = SearchOptions {
+                max_page_count: self.options.max_page_count,
+                case_sensitive: true,
+                exclude_sentence_boundary_tags: true,
+                ..Default::default()
+            };
+            let query = format!("{context} *");
+            let mut ngrams = Vec::new();
+            let mut pages = self.client.search(query, self.corpus, options);
+            while let Some(page) = pages.next().await {
+                ngrams.extend(page?.to_page().ngrams);
+            }
+            for ngram in ngrams {
+                let Some(word) = ngram.tokens.last() else {
+                    continue;
+                };
+                let word = word.text.clone();
+                let count = self.count_in_years(&ngram).await?;
+                if count > 0 {
+                    continuations.push((word, count));
+                }
+            }
+        }
+        self.continuations.insert(context, continuations.clone());
+        Ok(continuations)
+    }
+
+    async fn count_in_years(&self, ngram: &NgramLite) -> Result<u64, Error> {
+        let Some(years) = self.options.years else {
+            return Ok(ngram.abs_total_match_count);
+        };
+        Ok(self
+            .client
+            .get_ngram(self.corpus, &ngram.id)
+            .await?
+            .map_or(0, |ngram| ngram.match_count_in(years)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LanguageModelOptions, NgramLanguageModel};
+    use crate::testing::{ngram, page, total_counts, Fixtures};
+    use crate::{Client, Corpus, NgramLength, NgramStat};
+
+    fn model(fixtures: Fixtures, years: Option<(u16, u16)>) -> NgramLanguageModel {
+        let options = LanguageModelOptions {
+            order: NgramLength::new(3).unwrap(),
+            years: years.map(|(first, last)| (first..=last).into()),
+            ..Default::default()
+        };
+        let totals = total_counts(1900, &[1000, 1000]);
+        let client = Client::with_transport(fixtures);
+        NgramLanguageModel::new(client, Corpus::English, &totals, options).unwrap()
+    }
+
+    #[tokio::test]
+    async fn predict_with_backoff() {
+        let fixtures = Fixtures::default()
+            .search(
+                "the quick *",
+                &page(&[
+                    (&["the", "quick", "brown"], 10),
+                    (&["the", "quick", "fox"], 5),
+                ]),
+            )
+            .search("the quick", &page(&[(&["the", "quick"], 20)]))
+            .search(
+                "quick *",
+                &page(&[(&["quick", "and"], 40), (&["quick", "brown"], 30)]),
+            )
+            .search("quick", &page(&[(&["quick"], 100)]));
+        let mut model = model(fixtures, None);
+
+        let predictions = model.predict("over the quick", 3).await.unwrap();
+        let words: Vec<_> = predictions.iter().map(|p| p.word.as_str()).collect();
+        assert_eq!(words, ["brown", "fox", "and"]);
+        assert_eq!(predictions[0].score, 0.5);
+        assert!((predictions[2].score - 0.16).abs() < 1e-12);
+        let cached = model.cache_len();
+        let metrics = model.client.metrics();
+        model.predict("the quick", 3).await.unwrap();
+        assert_eq!(model.cache_len(), cached);
+        let hits = model.client.metrics().cache_hits - metrics.cache_hits;
+        assert_eq!(hits, 4);
+        assert_eq!(model.client.metrics().cache_misses, metrics.cache_misses);
+    }
+
+    #[tokio::test]
+    async fn empty_context_predicts_nothing() {
+        let fixtures = Fixtures::default().search("plugh", &page(&[(&["plugh"], 5)]));
+        let mut model = model(fixtures, None);
+        assert!(model.predict("xyzzy plugh", 3).await.unwrap().is_empty());
+        assert!(model.predict("", 3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn score_within_years() {
+        let mut fixtures = Fixtures::default().search(
+            "brown/quick/(quick brown)",
+            &page(&[(&["quick"], 0), (&["quick", "brown"], 0)]),
+        );
+        for (id, stats) in [
+            ("quick", vec![NgramStat::new(1900, 100, 0.0)]),
+            (
+                "quick_brown",
+                vec![NgramStat::new(1900, 25, 0.0), NgramStat::new(1901, 5, 0.0)],
+            ),
+        ] {
+            let mut ngram = ngram(stats);
+            ngram.id = id.to_string();
+            fixtures = fixtures.ngram(&ngram);
+        }
+        let mut model = model(fixtures, Some((1900, 1900)));
+
+        let quick: f64 = 100.5 / 1000.5;
+        let brown_after_quick = 0.25;
+        let score = model.score("quick brown").await.unwrap();
+        assert!((score - (quick * brown_after_quick).log10()).abs() < 1e-12);
+        assert_eq!(model.probability("", "brown").await.unwrap(), 0.5 / 1000.5);
+    }
+}
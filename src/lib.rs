@@ -2,33 +2,87 @@
 // https://ngrams.dev
 // License: MIT
 
-use reqwest::StatusCode;
+use crate::metrics::Metrics;
+use crate::middleware::{Chain, Middleware};
+pub use crate::pos::PartOfSpeech;
+pub use crate::time_series::{Measure, TimeSeries, YearRange};
+use crate::transport::Transport;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Cow;
 use std::ops::Deref;
+use std::sync::Arc;
 use std::{error, fmt};
 
+pub mod analytics;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod capture;
+pub mod collocation;
+pub mod compare;
+pub mod complete;
+pub mod dating;
+pub mod frequency;
+pub mod inflection;
+pub mod language_model;
+pub mod metrics;
+pub mod middleware;
+pub mod orthography;
+pub mod pivot;
+pub mod pos;
+pub mod rarity;
+pub mod significance;
+pub mod smoothing;
+#[cfg(test)]
+mod testing;
+pub mod text;
+pub mod time_series;
+#[cfg(feature = "tower")]
+pub mod tower;
+pub mod transport;
+pub mod variants;
+
 const BASE_URL: &str = "https://api.ngrams.dev";
 
 #[derive(Clone)]
 pub struct Client {
-    inner: reqwest::Client,
+    transport: Arc<dyn Transport>,
     user_agent: String,
+    metrics: Arc<Metrics>,
+    middleware: Chain,
 }
 
 impl Client {
+    #[cfg(feature = "reqwest")]
     pub fn new() -> Self {
+        Self::with_transport(transport::ReqwestTransport::default())
+    }
+
+    /// Creates a client that sends its requests through `transport`.
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Self {
         Self {
-            inner: reqwest::Client::new(),
+            transport: Arc::new(transport),
             user_agent: format!(
                 "{}/{}/{}",
                 env!("CARGO_PKG_NAME"),
                 env!("CARGO_PKG_VERSION"),
                 std::env::consts::OS
             ),
+            metrics: Arc::default(),
+            middleware: Chain::default(),
         }
     }
 
+    /// Appends a middleware to the chain that every request passes through.
+    pub fn with_middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Returns the metrics collected so far by this client and all of its clones.
+    pub fn metrics(&self) -> metrics::MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
     pub fn search<Q: Into<String>>(
         &self,
         query: Q,
@@ -39,38 +93,58 @@ impl Client {
     }
 
     pub async fn get_ngram(&self, corpus: Corpus, id: &str) -> Result<Option<Ngram>, Error> {
-        let res = internal::get(self, corpus, id).send().await?;
-        match res.status() {
-            StatusCode::OK => Ok(Some(res.json().await?)),
-            StatusCode::NOT_FOUND => Ok(None),
-            other => Err(Error::unexpected_status_code(other.as_u16())),
-        }
+        let res = internal::get(self, corpus, id, &[])
+            .await
+            .and_then(|res| match res.status {
+                200 => Ok(Some(serde_json::from_str(&res.body)?)),
+                404 => Ok(None),
+                other => Err(Error::unexpected_status_code(other)),
+            });
+        self.observe(res)
     }
 
     pub async fn get_corpus_info(&self, corpus: Corpus) -> Result<CorpusInfo, Error> {
-        let res = internal::get(self, corpus, "info").send().await?;
-        match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            other => Err(Error::unexpected_status_code(other.as_u16())),
-        }
+        let res = internal::get(self, corpus, "info", &[])
+            .await
+            .and_then(|res| match res.status {
+                200 => Ok(serde_json::from_str(&res.body)?),
+                other => Err(Error::unexpected_status_code(other)),
+            });
+        self.observe(res)
     }
 
     pub async fn get_total_counts(&self, corpus: Corpus) -> Result<TotalCounts, Error> {
-        let res = internal::get(self, corpus, "total_counts").send().await?;
-        match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            other => Err(Error::unexpected_status_code(other.as_u16())),
+        let res = internal::get(self, corpus, "total_counts", &[])
+            .await
+            .and_then(|res| match res.status {
+                200 => Ok(serde_json::from_str(&res.body)?),
+                other => Err(Error::unexpected_status_code(other)),
+            });
+        self.observe(res)
+    }
+
+    /// Counts lookups of a cache built on this client in the client metrics.
+    pub(crate) fn record_cache(&self, hits: usize, misses: usize) {
+        self.metrics.record_cache(hits as u64, misses as u64);
+    }
+
+    /// Counts a failed call in the client metrics.
+    fn observe<T>(&self, res: Result<T, Error>) -> Result<T, Error> {
+        if let Err(err) = &res {
+            self.metrics.record_error(err.kind());
         }
+        res
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl Default for Client {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Corpus {
     English,
     German,
@@ -87,7 +161,7 @@ impl Corpus {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SearchOptions {
     pub max_page_size: u8,
     pub max_page_count: u32,
@@ -126,6 +200,27 @@ impl SearchOptions {
         }
         flags
     }
+
+    /// Inverse of `to_flags`, keeping the page size and count of `options`.
+    #[cfg(feature = "tower")]
+    fn from_flags(flags: &str, options: Self) -> Self {
+        let has = |flag: &str| {
+            flags
+                .as_bytes()
+                .chunks(2)
+                .any(|chunk| chunk == flag.as_bytes())
+        };
+        Self {
+            case_sensitive: has("cs"),
+            collapse_result: has("cr"),
+            exclude_punctuation_marks: has("ep"),
+            exclude_sentence_boundary_tags: has("es"),
+            dont_interpret_query_operators: has("ri"),
+            dont_tokenize_query_terms: has("rt"),
+            dont_unicode_normalize_query: has("rn"),
+            ..options
+        }
+    }
 }
 
 impl Default for SearchOptions {
@@ -165,65 +260,47 @@ impl Pages {
         }
     }
 
-    pub async fn next(&mut self) -> Option<Result<PageView, Error>> {
+    pub async fn next(&mut self) -> Option<Result<PageView<'_>, Error>> {
         if self.options.max_page_count == 0 {
             return None;
         }
 
-        let max_page_size = self.options.max_page_size.to_string();
-        let mut params = vec![("query", self.query.as_str()), ("limit", &max_page_size)];
-
-        let flags = self.options.to_flags();
-        if !flags.is_empty() {
-            params.push(("flags", &flags));
-        }
-
-        if let Some(next) = &self.next {
-            params.push(("start", next));
+        let res = internal::search_page(
+            &self.client,
+            self.corpus,
+            &self.query,
+            self.options,
+            self.next.as_deref(),
+        )
+        .await;
+        let res = match res {
+            Ok(text) => {
+                self.payload = text; // NgramTokenView::text backing
+                serde_json::from_str::<internal::SearchResult>(&self.payload).map_err(Error::from)
+            }
+            Err(err) => Err(err),
+        };
+        match self.client.observe(res) {
+            Ok(res) => {
+                let token = res.next_page_token.map(Cow::into_owned);
+                advance(&mut self.options, &mut self.next, token);
+                Some(Ok(PageView {
+                    query_tokens: res.query_tokens,
+                    ngrams: res.ngrams,
+                }))
+            }
+            Err(err) => Some(Err(err)),
         }
+    }
+}
 
-        use internal::{get, ErrorResult, SearchResult};
-
-        match get(&self.client, self.corpus, "search")
-            .query(&params)
-            .send()
-            .await
-        {
-            Ok(res) => match res.status() {
-                StatusCode::OK => match res.text().await {
-                    Ok(text) => {
-                        self.payload = text; // NgramTokenView::text backing
-                        match serde_json::from_str::<SearchResult>(&self.payload) {
-                            Ok(res) => {
-                                if let Some(token) = res.next_page_token {
-                                    self.options.max_page_count -= 1;
-                                    self.next = Some(token.into());
-                                } else {
-                                    self.options.max_page_count = 0;
-                                    self.next = None;
-                                }
-                                Some(Ok(PageView {
-                                    query_tokens: res.query_tokens,
-                                    ngrams: res.ngrams,
-                                }))
-                            }
-                            Err(err) => Some(Err(Error::exception(err))),
-                        }
-                    }
-                    Err(err) => Some(Err(Error::exception(err))),
-                },
-                StatusCode::BAD_REQUEST => match res.json::<ErrorResult>().await {
-                    Ok(res) => Some(Err(Error::bad_input(BadInputError {
-                        code: res.error.code,
-                        query_tokens: res.query_tokens,
-                    }))),
-                    Err(err) => Some(Err(Error::exception(err))),
-                },
-                other => Some(Err(Error::unexpected_status_code(other.as_u16()))),
-            },
-            Err(err) => Some(Err(Error::connection(err))),
-        }
+fn advance(options: &mut SearchOptions, next: &mut Option<String>, token: Option<String>) {
+    if token.is_some() {
+        options.max_page_count -= 1;
+    } else {
+        options.max_page_count = 0;
     }
+    *next = token;
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -240,6 +317,15 @@ impl PageView<'_> {
     }
 }
 
+impl<'a> From<&'a Page> for PageView<'a> {
+    fn from(page: &'a Page) -> Self {
+        Self {
+            query_tokens: page.query_tokens.iter().map(QueryTokenView::from).collect(),
+            ngrams: page.ngrams.iter().map(NgramLiteView::from).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct QueryTokenView<'a> {
     pub kind: QueryTokenKind,
@@ -253,6 +339,15 @@ impl QueryTokenView<'_> {
     }
 }
 
+impl<'a> From<&'a QueryToken> for QueryTokenView<'a> {
+    fn from(token: &'a QueryToken) -> Self {
+        Self {
+            kind: token.kind,
+            text: Cow::Borrowed(&token.text),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum QueryTokenKind {
@@ -293,6 +388,18 @@ impl NgramLiteView<'_> {
     }
 }
 
+impl<'a> From<&'a NgramLite> for NgramLiteView<'a> {
+    fn from(ngram: &'a NgramLite) -> Self {
+        Self {
+            id: &ngram.id,
+            abs_total_match_count: ngram.abs_total_match_count,
+            rel_total_match_count: ngram.rel_total_match_count,
+            tokens: ngram.tokens.iter().map(NgramTokenView::from).collect(),
+            r#abstract: ngram.r#abstract,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct NgramTokenView<'a> {
     pub kind: NgramTokenKind,
@@ -310,6 +417,17 @@ impl NgramTokenView<'_> {
     }
 }
 
+impl<'a> From<&'a NgramToken> for NgramTokenView<'a> {
+    fn from(token: &'a NgramToken) -> Self {
+        Self {
+            kind: token.kind,
+            text: Cow::Borrowed(&token.text),
+            inserted: token.inserted,
+            completed: token.completed,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum NgramTokenKind {
@@ -328,13 +446,19 @@ pub enum NgramTokenKind {
     SentenceEnd,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Page {
     pub query_tokens: Vec<QueryToken>,
     pub ngrams: Vec<NgramLite>,
 }
 
+impl Page {
+    pub fn view(&self) -> PageView<'_> {
+        PageView::from(self)
+    }
+}
+
 impl From<&PageView<'_>> for Page {
     fn from(page: &PageView) -> Self {
         Self {
@@ -344,7 +468,7 @@ impl From<&PageView<'_>> for Page {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct QueryToken {
     pub kind: QueryTokenKind,
     pub text: String,
@@ -359,7 +483,7 @@ impl From<&QueryTokenView<'_>> for QueryToken {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct NgramLite {
     pub id: String,
@@ -382,7 +506,7 @@ impl From<&NgramLiteView<'_>> for NgramLite {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NgramToken {
     pub kind: NgramTokenKind,
     pub text: String,
@@ -403,7 +527,7 @@ impl From<&NgramTokenView<'_>> for NgramToken {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Ngram {
     pub id: String,
@@ -413,7 +537,14 @@ pub struct Ngram {
     pub stats: Vec<NgramStat>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Ngram {
+    /// Returns `None` if the number of tokens is not in `1..=5`.
+    pub fn length(&self) -> Option<NgramLength> {
+        NgramLength::new(self.tokens.len())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NgramStat {
     pub year: u16,
@@ -442,20 +573,20 @@ impl PartialEq for NgramStat {
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
-    source: Option<Box<dyn error::Error>>,
+    source: Option<Box<dyn error::Error + Send + Sync>>,
 }
 
 impl Error {
-    pub fn new(kind: ErrorKind, source: Option<Box<dyn error::Error>>) -> Self {
+    pub fn new(kind: ErrorKind, source: Option<Box<dyn error::Error + Send + Sync>>) -> Self {
         Self { kind, source }
     }
 
-    pub fn connection(err: reqwest::Error) -> Self {
+    pub fn connection(err: impl error::Error + Send + Sync + 'static) -> Self {
         Self::new(ErrorKind::Connection, Some(Box::new(err)))
     }
 
-    pub fn exception(err: impl error::Error + 'static) -> Self {
-        Self::new(ErrorKind::Connection, Some(Box::new(err)))
+    pub fn exception(err: impl error::Error + Send + Sync + 'static) -> Self {
+        Self::new(ErrorKind::Exception, Some(Box::new(err)))
     }
 
     pub fn bad_input(err: BadInputError) -> Self {
@@ -471,7 +602,7 @@ impl Error {
     }
 
     pub fn source(&self) -> Option<&dyn error::Error> {
-        self.source.as_deref()
+        self.source.as_deref().map(|err| err as &dyn error::Error)
     }
 
     pub fn into_bad_input_error(self) -> BadInputError {
@@ -481,7 +612,9 @@ impl Error {
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        self.source.as_deref()
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn error::Error + 'static))
     }
 }
 
@@ -495,13 +628,20 @@ impl fmt::Display for Error {
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Self::connection(err)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::exception(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ErrorKind {
     /// HTTP connection error.
     Connection,
@@ -564,6 +704,12 @@ pub struct CorpusInfo {
     pub stats: [CorpusStat; 5],
 }
 
+impl CorpusInfo {
+    pub fn stat(&self, len: NgramLength) -> &CorpusStat {
+        &self.stats[len.index()]
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct CorpusStat {
@@ -576,7 +722,67 @@ pub struct CorpusStat {
     pub max_total_match_count: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl CorpusStat {
+    pub fn years(&self) -> YearRange {
+        YearRange::new(self.min_year, self.max_year)
+    }
+}
+
+/// Number of tokens of an ngram, from 1 to 5.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NgramLength(u8);
+
+impl NgramLength {
+    pub const MIN: NgramLength = NgramLength(1);
+    pub const MAX: NgramLength = NgramLength(5);
+    pub const ALL: [NgramLength; 5] = [
+        NgramLength(1),
+        NgramLength(2),
+        NgramLength(3),
+        NgramLength(4),
+        NgramLength(5),
+    ];
+
+    pub fn new(len: usize) -> Option<Self> {
+        (1..=5).contains(&len).then_some(Self(len as u8))
+    }
+
+    pub fn get(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Index into `CorpusInfo::stats` and `TotalCounts::match_counts`.
+    fn index(self) -> usize {
+        self.get() - 1
+    }
+}
+
+impl TryFrom<usize> for NgramLength {
+    type Error = InvalidNgramLength;
+
+    fn try_from(len: usize) -> Result<Self, Self::Error> {
+        Self::new(len).ok_or(InvalidNgramLength(len))
+    }
+}
+
+impl fmt::Display for NgramLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidNgramLength(pub usize);
+
+impl fmt::Display for InvalidNgramLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid ngram length: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidNgramLength {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TotalCounts {
     pub min_year: u16,
@@ -584,7 +790,33 @@ pub struct TotalCounts {
     pub match_counts: [TotalCountsByYear; 5],
 }
 
-#[derive(Debug)]
+impl TotalCounts {
+    pub fn by_year(&self, len: NgramLength) -> &TotalCountsByYear {
+        &self.match_counts[len.index()]
+    }
+
+    /// Returns the total match count of all ngrams of length `len` in `year`,
+    /// or `None` if `year` is not within `min_year..=max_year`.
+    pub fn count(&self, len: NgramLength, year: u16) -> Option<u64> {
+        if !(self.min_year..=self.max_year).contains(&year) {
+            return None;
+        }
+        self.by_year(len)
+            .get((year - self.min_year) as usize)
+            .copied()
+    }
+
+    /// Iterates over the `(year, count)` pairs of ngrams of length `len`
+    /// from `min_year` to `max_year`.
+    pub fn counts(&self, len: NgramLength) -> impl Iterator<Item = (u16, u64)> + '_ {
+        let counts = self.by_year(len);
+        (self.min_year..=self.max_year)
+            .zip(counts.iter())
+            .map(|(year, &count)| (year, count))
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct TotalCountsByYear([u64; TOTAL_COUNTS_BY_YEAR_LEN]);
 pub const TOTAL_COUNTS_BY_YEAR_LEN: usize = 550;
 
@@ -627,17 +859,88 @@ impl<'de> Deserialize<'de> for TotalCountsByYear {
 
 /// Internal module containing implementation details.
 /// Used for benchmarking. Don't use directly.
-mod internal {
-    use crate::{Client, Corpus, ErrorCode, NgramLiteView, QueryToken, QueryTokenView, BASE_URL};
-    use reqwest::RequestBuilder;
+#[doc(hidden)]
+pub mod internal {
+    use crate::metrics::Endpoint;
+    use crate::middleware::{Request, Response};
+    use crate::{
+        BadInputError, Client, Corpus, ErrorCode, NgramLiteView, QueryToken, QueryTokenView,
+        SearchOptions,
+    };
     use serde::Deserialize;
     use std::borrow::Cow;
+    use std::time::Instant;
 
-    pub(crate) fn get(client: &Client, corpus: Corpus, resource: &str) -> RequestBuilder {
+    /// Sends a GET request through the client's middleware chain.
+    pub(crate) async fn get(
+        client: &Client,
+        corpus: Corpus,
+        resource: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Response, crate::Error> {
+        let mut request = Request::new(corpus, resource, query);
+        request.set_header("user-agent", client.user_agent.as_str());
         client
-            .inner
-            .get(format!("{}/{}/{}", BASE_URL, corpus.label(), resource))
-            .header("user-agent", &client.user_agent)
+            .middleware
+            .run(request, |request| send(client, request))
+            .await
+    }
+
+    async fn send(client: &Client, request: Request) -> Result<Response, crate::Error> {
+        let endpoint = Endpoint::from_resource(&request.resource);
+        client.metrics.record_request(endpoint);
+        let start = Instant::now();
+        let res = client.transport.send(request).await?;
+        client.metrics.record_response(
+            endpoint,
+            res.status,
+            start.elapsed(),
+            res.body.len() as u64,
+        );
+        Ok(res)
+    }
+
+    /// Fetches the search result page starting at `start` and returns its raw JSON payload.
+    pub(crate) async fn search_page(
+        client: &Client,
+        corpus: Corpus,
+        query: &str,
+        options: SearchOptions,
+        start: Option<&str>,
+    ) -> Result<String, crate::Error> {
+        let max_page_size = options.max_page_size.to_string();
+        let mut params = vec![("query", query), ("limit", &max_page_size)];
+
+        let flags = options.to_flags();
+        if !flags.is_empty() {
+            params.push(("flags", &flags));
+        }
+
+        if let Some(start) = start {
+            params.push(("start", start));
+        }
+
+        search(client, corpus, &params).await
+    }
+
+    /// Fetches a single search result page and returns its raw JSON payload.
+    pub async fn search(
+        client: &Client,
+        corpus: Corpus,
+        params: &[(&str, &str)],
+    ) -> Result<String, crate::Error> {
+        let res = get(client, corpus, "search", params).await?;
+        match res.status {
+            200 => Ok(res.body),
+            400 => {
+                let res = serde_json::from_str::<ErrorResult>(&res.body)?;
+                Err(crate::Error::bad_input(BadInputError {
+                    code: res.error.code,
+                    query_tokens: res.query_tokens,
+                }))
+            }
+            other => Err(crate::Error::unexpected_status_code(other)),
+        }
     }
 
     #[derive(Debug, Deserialize)]
@@ -667,8 +970,34 @@ mod internal {
 
 #[cfg(test)]
 mod tests {
+    use crate::testing::total_counts;
+    #[cfg(feature = "reqwest")]
     use crate::{BadInputError, Client, Corpus, ErrorCode, ErrorKind, SearchOptions};
+    use crate::{InvalidNgramLength, NgramLength};
+
+    #[test]
+    fn ngram_length_is_one_to_five() {
+        assert_eq!(NgramLength::new(0), None);
+        assert_eq!(NgramLength::new(5), Some(NgramLength::MAX));
+        assert_eq!(NgramLength::try_from(6), Err(InvalidNgramLength(6)));
+        assert_eq!(NgramLength::ALL.map(NgramLength::get), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn total_counts_respect_corpus_years() {
+        let mut totals = total_counts(1900, &[10, 20, 30]);
+        totals.max_year = 1901;
+        let len = NgramLength::new(3).unwrap();
+        assert_eq!(totals.count(len, 1899), None);
+        assert_eq!(totals.count(len, 1901), Some(20));
+        assert_eq!(totals.count(len, 1902), None);
+        assert_eq!(
+            totals.counts(len).collect::<Vec<_>>(),
+            [(1900, 10), (1901, 20)]
+        );
+    }
 
+    #[cfg(feature = "reqwest")]
     #[tokio::test]
     async fn search_and_fetch_first_three_pages() {
         let client = Client::new();
@@ -697,6 +1026,7 @@ mod tests {
         assert_eq!(num_ngrams, 300);
     }
 
+    #[cfg(feature = "reqwest")]
     #[tokio::test]
     async fn search_and_fetch_all_pages() {
         let client = Client::new();
@@ -727,6 +1057,7 @@ mod tests {
         assert_eq!(num_pages, 13);
     }
 
+    #[cfg(feature = "reqwest")]
     #[tokio::test]
     async fn check_error_invalid_parameter_limit() {
         let client = Client::new();
@@ -0,0 +1,273 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+use crate::ErrorKind;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of most recent requests used to compute latency percentiles.
+pub const LATENCY_WINDOW_LEN: usize = 1024;
+
+/// Server endpoint a request was sent to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Endpoint {
+    Search,
+    Ngram,
+    CorpusInfo,
+    TotalCounts,
+}
+
+impl Endpoint {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Search => "search",
+            Self::Ngram => "ngram",
+            Self::CorpusInfo => "corpus_info",
+            Self::TotalCounts => "total_counts",
+        }
+    }
+
+    pub(crate) fn from_resource(resource: &str) -> Self {
+        match resource {
+            "search" => Self::Search,
+            "info" => Self::CorpusInfo,
+            "total_counts" => Self::TotalCounts,
+            _ => Self::Ngram,
+        }
+    }
+}
+
+/// Point-in-time copy of the metrics collected by a [`Client`](crate::Client)
+/// and all of its clones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Number of requests sent per endpoint.
+    pub requests: HashMap<Endpoint, u64>,
+    /// Number of responses per HTTP status code.
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Latency percentiles over the last [`LATENCY_WINDOW_LEN`] responses.
+    pub latency: LatencyPercentiles,
+    /// Sum of all response body sizes in bytes.
+    pub bytes_received: u64,
+    /// Number of failed calls per error kind.
+    pub errors: HashMap<ErrorKind, u64>,
+    /// Lookups answered from the caches of a
+    /// [`RarityProfiler`](crate::rarity::RarityProfiler) or an
+    /// [`NgramLanguageModel`](crate::language_model::NgramLanguageModel).
+    pub cache_hits: u64,
+    /// Lookups of those caches that required a request.
+    pub cache_misses: u64,
+    /// Number of requests that waited for the service of a client built with
+    /// `Client::from_service` to become ready, e.g. because of a rate limit
+    /// layer.
+    pub rate_limit_waits: u64,
+    /// Total time of those waits.
+    pub rate_limit_wait_time: Duration,
+}
+
+impl MetricsSnapshot {
+    pub fn total_requests(&self) -> u64 {
+        self.requests.values().sum()
+    }
+
+    pub fn total_errors(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyPercentiles {
+    fn from_samples(samples: &VecDeque<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let at = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        Self {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Shared metrics recorder behind [`Client::metrics`](crate::Client::metrics).
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    inner: Mutex<Recorder>,
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    snapshot: MetricsSnapshot,
+    latencies: VecDeque<Duration>,
+}
+
+impl Metrics {
+    pub(crate) fn record_request(&self, endpoint: Endpoint) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.snapshot.requests.entry(endpoint).or_default() += 1;
+        #[cfg(feature = "metrics")]
+        facade::record_request(endpoint);
+    }
+
+    pub(crate) fn record_response(
+        &self,
+        endpoint: Endpoint,
+        status: u16,
+        latency: Duration,
+        bytes: u64,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.snapshot.status_codes.entry(status).or_default() += 1;
+        inner.snapshot.bytes_received += bytes;
+        if inner.latencies.len() == LATENCY_WINDOW_LEN {
+            inner.latencies.pop_front();
+        }
+        inner.latencies.push_back(latency);
+        #[cfg(feature = "metrics")]
+        facade::record_response(endpoint, status, latency, bytes);
+        #[cfg(not(feature = "metrics"))]
+        let _ = endpoint;
+    }
+
+    pub(crate) fn record_error(&self, kind: ErrorKind) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.snapshot.errors.entry(kind).or_default() += 1;
+        #[cfg(feature = "metrics")]
+        facade::record_error(kind);
+    }
+
+    pub(crate) fn record_cache(&self, hits: u64, misses: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.snapshot.cache_hits += hits;
+        inner.snapshot.cache_misses += misses;
+        #[cfg(feature = "metrics")]
+        facade::record_cache(hits, misses);
+    }
+
+    #[cfg(feature = "tower")]
+    pub(crate) fn record_rate_limit_wait(&self, wait: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.snapshot.rate_limit_waits += 1;
+        inner.snapshot.rate_limit_wait_time += wait;
+        #[cfg(feature = "metrics")]
+        facade::record_rate_limit_wait(wait);
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        let inner = self.inner.lock().unwrap();
+        MetricsSnapshot {
+            latency: LatencyPercentiles::from_samples(&inner.latencies),
+            ..inner.snapshot.clone()
+        }
+    }
+}
+
+/// Forwards every recorded event to the [`metrics`](https://docs.rs/metrics)
+/// facade, so that whatever recorder is installed (e.g. a Prometheus exporter)
+/// picks them up. Emitted metrics:
+///
+/// * `ngrams_requests_total` (counter, label `endpoint`)
+/// * `ngrams_responses_total` (counter, labels `endpoint`, `status`)
+/// * `ngrams_request_duration_seconds` (histogram, label `endpoint`)
+/// * `ngrams_received_bytes_total` (counter, label `endpoint`)
+/// * `ngrams_errors_total` (counter, label `kind`)
+/// * `ngrams_cache_hits_total` and `ngrams_cache_misses_total` (counters)
+/// * `ngrams_rate_limit_wait_seconds` (histogram)
+#[cfg(feature = "metrics")]
+mod facade {
+    use super::Endpoint;
+    use crate::ErrorKind;
+    use std::time::Duration;
+
+    pub(super) fn record_request(endpoint: Endpoint) {
+        ::metrics::counter!("ngrams_requests_total", "endpoint" => endpoint.label().to_string())
+            .increment(1);
+    }
+
+    pub(super) fn record_response(endpoint: Endpoint, status: u16, latency: Duration, bytes: u64) {
+        let endpoint = endpoint.label().to_string();
+        ::metrics::counter!(
+            "ngrams_responses_total",
+            "endpoint" => endpoint.clone(),
+            "status" => status.to_string()
+        )
+        .increment(1);
+        ::metrics::histogram!("ngrams_request_duration_seconds", "endpoint" => endpoint.clone())
+            .record(latency.as_secs_f64());
+        ::metrics::counter!("ngrams_received_bytes_total", "endpoint" => endpoint).increment(bytes);
+    }
+
+    pub(super) fn record_error(kind: ErrorKind) {
+        ::metrics::counter!("ngrams_errors_total", "kind" => format!("{:?}", kind)).increment(1);
+    }
+
+    pub(super) fn record_cache(hits: u64, misses: u64) {
+        ::metrics::counter!("ngrams_cache_hits_total").increment(hits);
+        ::metrics::counter!("ngrams_cache_misses_total").increment(misses);
+    }
+
+    #[cfg(feature = "tower")]
+    pub(super) fn record_rate_limit_wait(wait: Duration) {
+        ::metrics::histogram!("ngrams_rate_limit_wait_seconds").record(wait.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Endpoint, Metrics, LATENCY_WINDOW_LEN};
+    use crate::ErrorKind;
+    use std::time::Duration;
+
+    #[test]
+    fn snapshot_counts_requests_responses_and_errors() {
+        let metrics = Metrics::default();
+        metrics.record_request(Endpoint::Search);
+        metrics.record_request(Endpoint::Search);
+        metrics.record_request(Endpoint::Ngram);
+        metrics.record_response(Endpoint::Search, 200, Duration::from_millis(10), 100);
+        metrics.record_response(Endpoint::Search, 400, Duration::from_millis(20), 50);
+        metrics.record_response(Endpoint::Ngram, 200, Duration::from_millis(30), 25);
+        metrics.record_error(ErrorKind::BadInput);
+        metrics.record_cache(3, 1);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests[&Endpoint::Search], 2);
+        assert_eq!(snapshot.requests[&Endpoint::Ngram], 1);
+        assert_eq!(snapshot.total_requests(), 3);
+        assert_eq!(snapshot.status_codes[&200], 2);
+        assert_eq!(snapshot.status_codes[&400], 1);
+        assert_eq!(snapshot.bytes_received, 175);
+        assert_eq!(snapshot.errors[&ErrorKind::BadInput], 1);
+        assert_eq!((snapshot.cache_hits, snapshot.cache_misses), (3, 1));
+        assert_eq!(snapshot.latency.p50, Duration::from_millis(20));
+        assert_eq!(snapshot.latency.max, Duration::from_millis(30));
+    }
+
+    #[test]
+    fn latency_percentiles_use_most_recent_window() {
+        let metrics = Metrics::default();
+        metrics.record_response(Endpoint::Search, 200, Duration::from_secs(60), 0);
+        for ms in 1..=LATENCY_WINDOW_LEN as u64 {
+            metrics.record_response(Endpoint::Search, 200, Duration::from_millis(ms), 0);
+        }
+        let latency = metrics.snapshot().latency;
+        assert_eq!(
+            latency.max,
+            Duration::from_millis(LATENCY_WINDOW_LEN as u64)
+        );
+        assert_eq!(latency.p50, Duration::from_millis(513));
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+use crate::{Corpus, Error, BASE_URL};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Outgoing request as seen by a [`Middleware`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub corpus: Corpus,
+    /// Last path segment, e.g. `search`, `info`, `total_counts` or an ngram id.
+    pub resource: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub(crate) fn new(corpus: Corpus, resource: &str, query: &[(&str, &str)]) -> Self {
+        Self {
+            corpus,
+            resource: resource.to_string(),
+            query: query
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Returns the request URL without the query string.
+    pub fn url(&self) -> String {
+        format!("{}/{}/{}", BASE_URL, self.corpus.label(), self.resource)
+    }
+
+    /// Replaces the value of the given header or appends it if not yet present.
+    pub fn set_header<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        let key = key.into();
+        let value = value.into();
+        match self
+            .headers
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(&key))
+        {
+            Some((_, v)) => *v = value,
+            None => self.headers.push((key, value)),
+        }
+    }
+}
+
+/// Incoming response as seen by a [`Middleware`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new<B: Into<String>>(status: u16, body: B) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+/// Hooks that run around every request a [`Client`](crate::Client) sends,
+/// including each page fetched by [`Pages`](crate::Pages).
+///
+/// `before_request` hooks run in the order the middleware was added. A hook
+/// may short-circuit the chain by returning a synthetic response, in which
+/// case no further `before_request` hooks run and nothing is sent. The
+/// `after_response` hooks of all middleware whose `before_request` ran are
+/// then called in reverse order.
+pub trait Middleware: Send + Sync {
+    fn before_request(&self, _request: &mut Request) -> Option<Response> {
+        None
+    }
+
+    fn after_response(&self, _response: &Response) {}
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Chain(Vec<Arc<dyn Middleware>>);
+
+impl Chain {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.0.push(middleware);
+    }
+
+    pub(crate) async fn run<F, Fut>(&self, mut request: Request, send: F) -> Result<Response, Error>
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = Result<Response, Error>>,
+    {
+        let mut num_run = 0;
+        let mut synthetic = None;
+        for middleware in &self.0 {
+            num_run += 1;
+            synthetic = middleware.before_request(&mut request);
+            if synthetic.is_some() {
+                break;
+            }
+        }
+        let response = match synthetic {
+            Some(response) => response,
+            None => send(request).await?,
+        };
+        for middleware in self.0[..num_run].iter().rev() {
+            middleware.after_response(&response);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Middleware, Request, Response};
+    use crate::testing::Fixtures;
+    use crate::{Client, Corpus, SearchOptions};
+    use std::sync::{Arc, Mutex};
+
+    struct Canned(&'static str);
+
+    impl Middleware for Canned {
+        fn before_request(&self, request: &mut Request) -> Option<Response> {
+            match request.resource.as_str() {
+                "info" => Some(Response::new(500, "")),
+                "search" => Some(Response::new(200, self.0)),
+                _ => Some(Response::new(404, "")),
+            }
+        }
+    }
+
+    struct Log(Arc<Mutex<Vec<String>>>, &'static str);
+
+    impl Middleware for Log {
+        fn before_request(&self, request: &mut Request) -> Option<Response> {
+            request.set_header("x-tenant", self.1);
+            self.0.lock().unwrap().push(format!("before {}", self.1));
+            None
+        }
+
+        fn after_response(&self, response: &Response) {
+            let entry = format!("after {} {}", self.1, response.status);
+            self.0.lock().unwrap().push(entry);
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_run_in_order_and_short_circuit() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::with_transport(Fixtures::default())
+            .with_middleware(Log(log.clone(), "a"))
+            .with_middleware(Log(log.clone(), "b"))
+            .with_middleware(Canned(""));
+
+        assert_eq!(client.get_ngram(Corpus::English, "x").await.unwrap(), None);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["before a", "before b", "after b 404", "after a 404"]
+        );
+        assert!(client.get_corpus_info(Corpus::English).await.is_err());
+        assert_eq!(client.metrics().total_requests(), 0);
+    }
+
+    #[tokio::test]
+    async fn search_pages_pass_through_middleware() {
+        let payload = r#"{"queryTokens":[{"kind":"TERM","text":"hello"}],"ngrams":[]}"#;
+        let client = Client::with_transport(Fixtures::default()).with_middleware(Canned(payload));
+
+        let mut pages = client.search("hello", Corpus::English, SearchOptions::default());
+        let page = pages.next().await.unwrap().unwrap();
+        assert_eq!(page.query_tokens.len(), 1);
+        assert!(page.ngrams.is_empty());
+        assert!(pages.next().await.is_none());
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Historical spellings of modern German and English words.
+//!
+//! Spelling reforms split the history of many words across several forms,
+//! e.g. German `Thür` became `Tür` in 1901 and `daß` became `dass` in 1996,
+//! and English `connexion` gave way to `connection`. The rule tables below
+//! turn a modern word into its plausible historical forms, which are then
+//! searched with one alternation query. Rules overgenerate on purpose:
+//! spellings that never existed are simply not found.
+
+use crate::compare::alternation;
+use crate::variants::VariantGroup;
+use crate::{Client, Corpus, Error};
+use std::collections::HashMap;
+
+/// Upper limit of the number of spellings generated for a word.
+const MAX_SPELLINGS: usize = 32;
+
+/// Where in a word the modern spelling of a [`Rule`] is replaced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// The whole word.
+    Word,
+    Start,
+    End,
+    /// Every occurrence.
+    Anywhere,
+    /// Every occurrence except at the end of the word, e.g. the long s.
+    NotFinal,
+}
+
+/// Replacement of a modern spelling by a historical one. Both are lowercase.
+/// Tokens of historical spellings that the corpus splits, like `to - day`,
+/// are separated by spaces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub modern: &'static str,
+    pub historical: &'static str,
+    pub position: Position,
+    /// Year of the reform that abolished the historical spelling, or of when
+    /// it fell out of use.
+    pub until: u16,
+}
+
+const fn rule(
+    modern: &'static str,
+    historical: &'static str,
+    position: Position,
+    until: u16,
+) -> Rule {
+    Rule {
+        modern,
+        historical,
+        position,
+        until,
+    }
+}
+
+/// Second Orthographic Conference of 1901, reform of 1996, and the long s of
+/// Fraktur typefaces, abolished in 1941.
+const GERMAN: &[Rule] = &[
+    rule("ss", "ß", Position::End, 1996),
+    rule("sst", "ßt", Position::Anywhere, 1996),
+    rule("nis", "niß", Position::End, 1996),
+    rule("ta", "tha", Position::Start, 1901),
+    rule("te", "the", Position::Start, 1901),
+    rule("ti", "thi", Position::Start, 1901),
+    rule("tu", "thu", Position::Start, 1901),
+    rule("tü", "thü", Position::Start, 1901),
+    rule("ut", "uth", Position::End, 1901),
+    rule("at", "ath", Position::End, 1901),
+    rule("ert", "erth", Position::End, 1901),
+    rule("ei", "ey", Position::End, 1901),
+    rule("ein", "eyn", Position::End, 1901),
+    rule("k", "c", Position::Start, 1901),
+    rule("z", "c", Position::Start, 1901),
+    rule("kt", "ct", Position::Anywhere, 1901),
+    rule("ieren", "iren", Position::End, 1901),
+    rule("iert", "irt", Position::End, 1901),
+    rule("foto", "photo", Position::Start, 1996),
+    rule("graf", "graph", Position::Anywhere, 1996),
+    rule("s", "ſ", Position::NotFinal, 1941),
+];
+
+/// Spellings common in the 18th and 19th century, and the long s, which fell
+/// out of use around 1800.
+const ENGLISH: &[Rule] = &[
+    rule("today", "to - day", Position::Word, 1930),
+    rule("tomorrow", "to - morrow", Position::Word, 1930),
+    rule("tonight", "to - night", Position::Word, 1930),
+    rule("show", "shew", Position::Word, 1850),
+    rule("shown", "shewn", Position::Word, 1850),
+    rule("ection", "exion", Position::End, 1900),
+    rule("ic", "ick", Position::End, 1800),
+    rule("s", "ſ", Position::NotFinal, 1800),
+];
+
+/// Rule table of `corpus`. Russian has none.
+pub fn rules(corpus: Corpus) -> &'static [Rule] {
+    match corpus {
+        Corpus::English => ENGLISH,
+        Corpus::German => GERMAN,
+        Corpus::Russian => &[],
+    }
+}
+
+/// `word` followed by all spellings produced by any combination of the rules
+/// of `corpus`, in the case of `word`: lowercase, capitalized or all caps.
+pub fn spellings(word: &str, corpus: Corpus) -> Vec<String> {
+    spellings_until(word, corpus)
+        .into_iter()
+        .map(|(spelling, _)| spelling)
+        .collect()
+}
+
+/// [`spellings`] of `word`, each with the earliest [`Rule::until`] of the
+/// rules that produced it, `None` for `word` itself.
+fn spellings_until(word: &str, corpus: Corpus) -> Vec<(String, Option<u16>)> {
+    let mut spellings: Vec<(String, Option<u16>)> = vec![(word.to_lowercase(), None)];
+    for rule in rules(corpus) {
+        for i in 0..spellings.len() {
+            let Some(spelling) = apply(rule, &spellings[i].0) else {
+                continue;
+            };
+            let until = spellings[i]
+                .1
+                .map_or(rule.until, |until| until.min(rule.until));
+            if spellings.len() < MAX_SPELLINGS && spellings.iter().all(|(s, _)| *s != spelling) {
+                spellings.push((spelling, Some(until)));
+            }
+        }
+    }
+    let mut letters = word.chars().filter(|c| c.is_alphabetic()).peekable();
+    let all_caps = letters.peek().is_some() && letters.all(char::is_uppercase);
+    let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+    spellings[0].0 = word.to_string();
+    for (spelling, _) in &mut spellings[1..] {
+        if all_caps {
+            *spelling = uppercase(spelling);
+        } else if capitalized {
+            *spelling = capitalize(spelling);
+        }
+    }
+    spellings
+}
+
+/// Alternation of all [`spellings`] of `word`, e.g. `Tür/Thür`.
+pub fn query(word: &str, corpus: Corpus) -> String {
+    let spellings = spellings(word, corpus);
+    alternation(&spellings.iter().collect::<Vec<_>>())
+}
+
+fn apply(rule: &Rule, word: &str) -> Option<String> {
+    match rule.position {
+        Position::Word => (word == rule.modern).then(|| rule.historical.to_string()),
+        Position::Start => word
+            .strip_prefix(rule.modern)
+            .filter(|rest| !rest.is_empty())
+            .map(|rest| format!("{}{rest}", rule.historical)),
+        Position::End => word
+            .strip_suffix(rule.modern)
+            .filter(|rest| !rest.is_empty())
+            .map(|rest| format!("{rest}{}", rule.historical)),
+        Position::Anywhere => word
+            .contains(rule.modern)
+            .then(|| word.replace(rule.modern, rule.historical)),
+        Position::NotFinal => {
+            let (rest, last) = word.split_at(word.char_indices().last()?.0);
+            rest.contains(rule.modern)
+                .then(|| format!("{}{last}", rest.replace(rule.modern, rule.historical)))
+        }
+    }
+}
+
+/// Uppercase of `word`, except for `ß` and `ſ`, which historical spellings
+/// keep in capitals, e.g. `DAß`.
+fn uppercase(word: &str) -> String {
+    let mut uppercase = String::with_capacity(word.len());
+    for c in word.chars() {
+        if matches!(c, 'ß' | 'ſ') {
+            uppercase.push(c);
+        } else {
+            uppercase.extend(c.to_uppercase());
+        }
+    }
+    uppercase
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Historical spelling found in the corpus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoricalSpelling {
+    pub text: String,
+    /// Year in which the spelling was abolished or fell out of use, the
+    /// earliest [`Rule::until`] of the rules that produced it.
+    pub until: u16,
+    /// Matches in the years after `until`.
+    pub matches_after: u64,
+}
+
+impl HistoricalSpelling {
+    /// Whether the spelling is still attested after it was abolished.
+    pub fn is_attested_after(&self) -> bool {
+        self.matches_after > 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpellingHistory {
+    /// All spellings found, including the modern one, the most frequent
+    /// first.
+    pub group: VariantGroup,
+    /// The historical spellings among the members of the group, in the same
+    /// order.
+    pub historical: Vec<HistoricalSpelling>,
+}
+
+impl Client {
+    /// Searches all historical [`spellings`] of `word` and combines their
+    /// series. For each historical spelling found, the matches after the year
+    /// it was abolished are counted.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ngrams::Client) -> Result<(), ngrams::Error> {
+    /// use ngrams::Corpus;
+    ///
+    /// let history = client.spelling_history("dass", Corpus::German).await?;
+    /// for spelling in &history.historical {
+    ///     if spelling.is_attested_after() {
+    ///         println!("{} after {}: {}", spelling.text, spelling.until, spelling.matches_after);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn spelling_history(
+        &self,
+        word: &str,
+        corpus: Corpus,
+    ) -> Result<SpellingHistory, Error> {
+        let spellings = spellings_until(word, corpus);
+        let texts: Vec<_> = spellings.iter().map(|(text, _)| text.clone()).collect();
+        let found = self.find_phrases(corpus, &texts, false).await?;
+        let mut members = Vec::new();
+        let mut untils = HashMap::new();
+        for (text, until) in spellings {
+            let Some(lite) = found.get(&text) else {
+                continue;
+            };
+            if let Some(ngram) = self.get_ngram(corpus, &lite.id).await? {
+                if let Some(until) = until {
+                    untils.insert(ngram.id.clone(), (text, until));
+                }
+                members.push(ngram);
+            }
+        }
+        let group = VariantGroup::new(word.to_string(), members);
+        let historical = group
+            .members
+            .iter()
+            .filter_map(|ngram| {
+                let (text, until) = untils.remove(&ngram.id)?;
+                let matches_after = ngram
+                    .stats
+                    .iter()
+                    .filter(|stat| stat.year > until)
+                    .map(|stat| stat.abs_match_count)
+                    .sum();
+                Some(HistoricalSpelling {
+                    text,
+                    until,
+                    matches_after,
+                })
+            })
+            .collect();
+        Ok(SpellingHistory { group, historical })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{query, spellings, HistoricalSpelling};
+    use crate::testing::Fixtures;
+    use crate::{Client, Corpus, NgramStat};
+
+    #[test]
+    fn german_and_english_spellings() {
+        assert_eq!(spellings("dass", Corpus::German), ["dass", "daß", "daſs"]);
+        assert!(spellings("Tür", Corpus::German).contains(&"Thür".to_string()));
+        assert!(spellings("sein", Corpus::German).contains(&"seyn".to_string()));
+        assert!(spellings("Geschichte", Corpus::German).contains(&"Geſchichte".to_string()));
+        assert!(spellings("Klasse", Corpus::German).contains(&"Claſſe".to_string()));
+        assert!(spellings("connection", Corpus::English).contains(&"connexion".to_string()));
+        assert!(spellings("public", Corpus::English).contains(&"publick".to_string()));
+        assert_eq!(query("today", Corpus::English), "today/(to - day)");
+        assert_eq!(spellings("Haus", Corpus::Russian), ["Haus"]);
+        assert_eq!(spellings("DASS", Corpus::German), ["DASS", "DAß", "DAſS"]);
+        assert!(spellings("TODAY", Corpus::English).contains(&"TO - DAY".to_string()));
+    }
+
+    #[tokio::test]
+    async fn combined_history_of_spellings() {
+        let fixtures = Fixtures::default().found(
+            "Tür/Thür",
+            &[
+                (&["Tür"], vec![NgramStat::new(1901, 5, 0.0)]),
+                (
+                    &["Thür"],
+                    vec![NgramStat::new(1900, 6, 0.0), NgramStat::new(1902, 2, 0.0)],
+                ),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+        let history = client
+            .spelling_history("Tür", Corpus::German)
+            .await
+            .unwrap();
+        assert_eq!(history.group.total, 13);
+        assert_eq!(history.group.members[0].id, "Thür");
+        assert_eq!(history.group.series.values(), [6.0, 5.0, 2.0]);
+        assert_eq!(
+            history.historical,
+            [HistoricalSpelling {
+                text: "Thür".to_string(),
+                until: 1901,
+                matches_after: 2,
+            }]
+        );
+        assert!(history.historical[0].is_attested_after());
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Frequency tables of the fill-ins of wildcard, alternation and prefix
+//! positions, see [`capture`](crate::capture).
+
+use crate::capture::Capture;
+use crate::pos::Tagged;
+use crate::{Error, NgramLite, Page, Pages, QueryToken};
+use std::collections::HashMap;
+
+/// What fill-ins are grouped by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Grouping {
+    /// Text of the captured tokens, e.g. `tea`.
+    #[default]
+    Text,
+    /// Part-of-speech tag of the captured tokens, e.g. `NOUN`. Untagged
+    /// tokens are grouped under the empty string.
+    Pos,
+    /// Text and tag of the captured tokens, e.g. `tea_NOUN`.
+    TextAndPos,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PivotOptions {
+    /// Groups fill-ins that only differ in case under their lowercase form.
+    pub case_fold: bool,
+    pub grouping: Grouping,
+}
+
+/// Distinct fill-ins with their summed `abs_total_match_count`, most frequent
+/// first and ties in lexicographic order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrequencyTable {
+    pub rows: Vec<(String, u64)>,
+}
+
+impl FrequencyTable {
+    pub fn total(&self) -> u64 {
+        self.rows.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.rows
+            .iter()
+            .find(|(other, _)| other == key)
+            .map(|&(_, count)| count)
+    }
+}
+
+/// Counts of pairs of fill-ins. Rows and columns are sorted by their totals,
+/// most frequent first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrossTable {
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+    /// `counts[row][column]`.
+    pub counts: Vec<Vec<u64>>,
+}
+
+impl CrossTable {
+    pub fn get(&self, row: &str, column: &str) -> Option<u64> {
+        let row = self.rows.iter().position(|key| key == row)?;
+        let column = self.columns.iter().position(|key| key == column)?;
+        Some(self.counts[row][column])
+    }
+}
+
+/// Aggregates the `n`-th capture of ngrams, as numbered by
+/// [`NgramLite::captures`].
+#[derive(Clone, Debug)]
+pub struct Pivot {
+    capture: usize,
+    options: PivotOptions,
+    counts: HashMap<String, u64>,
+}
+
+impl Pivot {
+    pub fn new(capture: usize, options: PivotOptions) -> Self {
+        Self {
+            capture,
+            options,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns `false` and ignores the ngram if it has no such capture.
+    pub fn add(&mut self, ngram: &NgramLite, query: &[QueryToken]) -> bool {
+        let Some(captures) = ngram.captures(query) else {
+            return false;
+        };
+        let Some(capture) = captures.get(self.capture) else {
+            return false;
+        };
+        *self.counts.entry(key(capture, self.options)).or_default() += ngram.abs_total_match_count;
+        true
+    }
+
+    pub fn add_page(&mut self, page: &Page) {
+        for ngram in &page.ngrams {
+            self.add(ngram, &page.query_tokens);
+        }
+    }
+
+    /// Adds all remaining pages of a search.
+    pub async fn add_pages(&mut self, pages: &mut Pages) -> Result<(), Error> {
+        while let Some(page) = pages.next().await {
+            self.add_page(&page?.to_page());
+        }
+        Ok(())
+    }
+
+    pub fn table(&self) -> FrequencyTable {
+        FrequencyTable {
+            rows: sorted(self.counts.clone()),
+        }
+    }
+}
+
+/// Aggregates pairs of captures of ngrams, e.g. both wildcards of `* of *`.
+#[derive(Clone, Debug)]
+pub struct CrossPivot {
+    row: usize,
+    column: usize,
+    options: PivotOptions,
+    counts: HashMap<(String, String), u64>,
+}
+
+impl CrossPivot {
+    pub fn new(row: usize, column: usize, options: PivotOptions) -> Self {
+        Self {
+            row,
+            column,
+            options,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns `false` and ignores the ngram if it lacks one of the captures.
+    pub fn add(&mut self, ngram: &NgramLite, query: &[QueryToken]) -> bool {
+        let Some(captures) = ngram.captures(query) else {
+            return false;
+        };
+        let (Some(row), Some(column)) = (captures.get(self.row), captures.get(self.column)) else {
+            return false;
+        };
+        let key = (key(row, self.options), key(column, self.options));
+        *self.counts.entry(key).or_default() += ngram.abs_total_match_count;
+        true
+    }
+
+    pub fn add_page(&mut self, page: &Page) {
+        for ngram in &page.ngrams {
+            self.add(ngram, &page.query_tokens);
+        }
+    }
+
+    /// Adds all remaining pages of a search.
+    pub async fn add_pages(&mut self, pages: &mut Pages) -> Result<(), Error> {
+        while let Some(page) = pages.next().await {
+            self.add_page(&page?.to_page());
+        }
+        Ok(())
+    }
+
+    pub fn table(&self) -> CrossTable {
+        let mut row_totals = HashMap::new();
+        let mut column_totals = HashMap::new();
+        for ((row, column), &count) in &self.counts {
+            *row_totals.entry(row.clone()).or_default() += count;
+            *column_totals.entry(column.clone()).or_default() += count;
+        }
+        let rows: Vec<_> = sorted(row_totals).into_iter().map(|(key, _)| key).collect();
+        let columns: Vec<_> = sorted(column_totals)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        let counts = rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        let key = (row.clone(), column.clone());
+                        self.counts.get(&key).copied().unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect();
+        CrossTable {
+            rows,
+            columns,
+            counts,
+        }
+    }
+}
+
+fn sorted(counts: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut rows: Vec<_> = counts.into_iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    rows
+}
+
+fn key(capture: &Capture, options: PivotOptions) -> String {
+    let keys: Vec<_> = capture
+        .tokens
+        .iter()
+        .map(|token| {
+            let text = || {
+                if options.case_fold {
+                    token.text.to_lowercase()
+                } else {
+                    token.text.clone()
+                }
+            };
+            match options.grouping {
+                Grouping::Text => text(),
+                Grouping::Pos => token.pos().map(|pos| pos.to_string()).unwrap_or_default(),
+                Grouping::TextAndPos => Tagged::new(&text(), token.pos()).to_string(),
+            }
+        })
+        .collect();
+    keys.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrossPivot, Grouping, Pivot, PivotOptions};
+    use crate::testing::{self, query_tokens};
+    use crate::{NgramTokenKind, Page, QueryTokenKind};
+
+    fn page(query: &[(QueryTokenKind, &str)], ngrams: &[(&[&str], u64)]) -> Page {
+        Page {
+            query_tokens: query_tokens(query),
+            ..testing::page(ngrams)
+        }
+    }
+
+    #[test]
+    fn frequency_table_of_fill_ins() {
+        let mut page = page(
+            &[
+                (QueryTokenKind::Term, "the"),
+                (QueryTokenKind::StarNoun, "*_NOUN"),
+                (QueryTokenKind::Term, "of"),
+            ],
+            &[
+                (&["the", "end", "of"], 5),
+                (&["the", "End", "of"], 2),
+                (&["the", "top", "of"], 6),
+                (&["the", "of"], 100),
+            ],
+        );
+        for ngram in &mut page.ngrams {
+            if let Some(token) = ngram.tokens.get_mut(1) {
+                token.kind = NgramTokenKind::TaggedAsNoun;
+            }
+        }
+
+        let mut pivot = Pivot::new(0, PivotOptions::default());
+        pivot.add_page(&page);
+        let table = pivot.table();
+        assert_eq!(table.rows[0], ("top".to_string(), 6));
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.total(), 13);
+
+        let options = PivotOptions {
+            case_fold: true,
+            grouping: Grouping::TextAndPos,
+        };
+        let mut pivot = Pivot::new(0, options);
+        pivot.add_page(&page);
+        assert_eq!(pivot.table().get("end_NOUN"), Some(7));
+
+        let options = PivotOptions {
+            grouping: Grouping::Pos,
+            ..Default::default()
+        };
+        let mut pivot = Pivot::new(0, options);
+        pivot.add_page(&page);
+        assert_eq!(pivot.table().rows, [("NOUN".to_string(), 13)]);
+    }
+
+    #[test]
+    fn cross_table_of_two_positions() {
+        let page = page(
+            &[
+                (QueryTokenKind::Star, "*"),
+                (QueryTokenKind::Term, "of"),
+                (QueryTokenKind::Star, "*"),
+            ],
+            &[
+                (&["cup", "of", "tea"], 10),
+                (&["cup", "of", "coffee"], 8),
+                (&["pot", "of", "tea"], 3),
+            ],
+        );
+        let mut pivot = CrossPivot::new(0, 1, PivotOptions::default());
+        pivot.add_page(&page);
+        let table = pivot.table();
+        assert_eq!(table.rows, ["cup", "pot"]);
+        assert_eq!(table.columns, ["tea", "coffee"]);
+        assert_eq!(table.counts, [[10, 8], [3, 0]]);
+        assert_eq!(table.get("pot", "tea"), Some(3));
+        assert_eq!(table.get("pot", "milk"), None);
+    }
+}
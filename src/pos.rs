@@ -0,0 +1,362 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Part-of-speech tags and the part-of-speech distribution of a word.
+
+use crate::{
+    Client, Corpus, Error, Measure, NgramToken, NgramTokenKind, NgramTokenView, QueryTokenKind,
+    SearchOptions, TimeSeries,
+};
+use std::cmp::Reverse;
+use std::str::FromStr;
+use std::{error, fmt};
+
+/// Universal part-of-speech tag as used by the corpora, written as a `_NOUN`
+/// style suffix in queries and ngram ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PartOfSpeech {
+    Adj,
+    Adp,
+    Adv,
+    Conj,
+    Det,
+    Noun,
+    Num,
+    Pron,
+    Prt,
+    Verb,
+}
+
+impl PartOfSpeech {
+    pub const ALL: [PartOfSpeech; 10] = [
+        PartOfSpeech::Adj,
+        PartOfSpeech::Adp,
+        PartOfSpeech::Adv,
+        PartOfSpeech::Conj,
+        PartOfSpeech::Det,
+        PartOfSpeech::Noun,
+        PartOfSpeech::Num,
+        PartOfSpeech::Pron,
+        PartOfSpeech::Prt,
+        PartOfSpeech::Verb,
+    ];
+
+    /// Tag without the leading underscore, e.g. `NOUN`.
+    pub fn tag(self) -> &'static str {
+        match self {
+            PartOfSpeech::Adj => "ADJ",
+            PartOfSpeech::Adp => "ADP",
+            PartOfSpeech::Adv => "ADV",
+            PartOfSpeech::Conj => "CONJ",
+            PartOfSpeech::Det => "DET",
+            PartOfSpeech::Noun => "NOUN",
+            PartOfSpeech::Num => "NUM",
+            PartOfSpeech::Pron => "PRON",
+            PartOfSpeech::Prt => "PRT",
+            PartOfSpeech::Verb => "VERB",
+        }
+    }
+}
+
+impl fmt::Display for PartOfSpeech {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl FromStr for PartOfSpeech {
+    type Err = ParsePartOfSpeechError;
+
+    /// Parses a tag like `NOUN`.
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        PartOfSpeech::ALL
+            .into_iter()
+            .find(|pos| pos.tag() == tag)
+            .ok_or_else(|| ParsePartOfSpeechError(tag.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePartOfSpeechError(pub String);
+
+impl fmt::Display for ParsePartOfSpeechError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown part-of-speech tag: {}", self.0)
+    }
+}
+
+impl error::Error for ParsePartOfSpeechError {}
+
+impl From<PartOfSpeech> for NgramTokenKind {
+    fn from(pos: PartOfSpeech) -> Self {
+        match pos {
+            PartOfSpeech::Adj => NgramTokenKind::TaggedAsAdj,
+            PartOfSpeech::Adp => NgramTokenKind::TaggedAsAdp,
+            PartOfSpeech::Adv => NgramTokenKind::TaggedAsAdv,
+            PartOfSpeech::Conj => NgramTokenKind::TaggedAsConj,
+            PartOfSpeech::Det => NgramTokenKind::TaggedAsDet,
+            PartOfSpeech::Noun => NgramTokenKind::TaggedAsNoun,
+            PartOfSpeech::Num => NgramTokenKind::TaggedAsNum,
+            PartOfSpeech::Pron => NgramTokenKind::TaggedAsPron,
+            PartOfSpeech::Prt => NgramTokenKind::TaggedAsPrt,
+            PartOfSpeech::Verb => NgramTokenKind::TaggedAsVerb,
+        }
+    }
+}
+
+impl From<PartOfSpeech> for QueryTokenKind {
+    fn from(pos: PartOfSpeech) -> Self {
+        match pos {
+            PartOfSpeech::Adj => QueryTokenKind::StarAdj,
+            PartOfSpeech::Adp => QueryTokenKind::StarAdp,
+            PartOfSpeech::Adv => QueryTokenKind::StarAdv,
+            PartOfSpeech::Conj => QueryTokenKind::StarConj,
+            PartOfSpeech::Det => QueryTokenKind::StarDet,
+            PartOfSpeech::Noun => QueryTokenKind::StarNoun,
+            PartOfSpeech::Num => QueryTokenKind::StarNum,
+            PartOfSpeech::Pron => QueryTokenKind::StarPron,
+            PartOfSpeech::Prt => QueryTokenKind::StarPrt,
+            PartOfSpeech::Verb => QueryTokenKind::StarVerb,
+        }
+    }
+}
+
+impl NgramTokenKind {
+    /// Part of speech of a tagged token.
+    pub fn pos(self) -> Option<PartOfSpeech> {
+        PartOfSpeech::ALL
+            .into_iter()
+            .find(|&pos| NgramTokenKind::from(pos) == self)
+    }
+}
+
+impl NgramToken {
+    /// Part of speech if the token is tagged.
+    pub fn pos(&self) -> Option<PartOfSpeech> {
+        self.kind.pos()
+    }
+}
+
+impl NgramTokenView<'_> {
+    /// Part of speech if the token is tagged.
+    pub fn pos(&self) -> Option<PartOfSpeech> {
+        self.kind.pos()
+    }
+}
+
+impl QueryTokenKind {
+    /// Part of speech of a tagged wildcard like `*_NOUN`.
+    pub fn star_pos(self) -> Option<PartOfSpeech> {
+        PartOfSpeech::ALL
+            .into_iter()
+            .find(|&pos| QueryTokenKind::from(pos) == self)
+    }
+}
+
+/// Word or wildcard with an optional part-of-speech suffix, e.g. `run_VERB`
+/// or `*_NOUN`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tagged<'a> {
+    pub word: &'a str,
+    pub pos: Option<PartOfSpeech>,
+}
+
+impl<'a> Tagged<'a> {
+    pub fn new(word: &'a str, pos: Option<PartOfSpeech>) -> Self {
+        Self { word, pos }
+    }
+
+    /// Splits off a known tag after the last underscore. Anything else,
+    /// including sentence boundary tags like `_END_`, is taken as untagged.
+    pub fn parse(text: &'a str) -> Self {
+        match text.rsplit_once('_') {
+            Some((word, tag)) if !word.is_empty() => match tag.parse() {
+                Ok(pos) => Self::new(word, Some(pos)),
+                Err(_) => Self::new(text, None),
+            },
+            _ => Self::new(text, None),
+        }
+    }
+}
+
+impl fmt::Display for Tagged<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pos {
+            Some(pos) => write!(f, "{}_{pos}", self.word),
+            None => f.write_str(self.word),
+        }
+    }
+}
+
+/// Matches of a word tagged as one part of speech.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PosCount {
+    pub pos: PartOfSpeech,
+    pub total: u64,
+    /// Absolute match counts by year.
+    pub series: TimeSeries,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PosProfile {
+    pub word: String,
+    /// Parts of speech the word is attested as, most frequent first.
+    pub counts: Vec<PosCount>,
+}
+
+impl PosProfile {
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|count| count.total).sum()
+    }
+
+    /// Share of all matches of the word that are tagged as `pos`.
+    pub fn share(&self, pos: PartOfSpeech) -> f64 {
+        let total = self.total();
+        match self.counts.iter().find(|count| count.pos == pos) {
+            Some(count) if total > 0 => count.total as f64 / total as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Share of each part of speech per year, in the order of `counts`. All
+    /// series cover the same years; years without any match have a share of
+    /// zero.
+    pub fn shares_by_year(&self) -> Vec<(PartOfSpeech, TimeSeries)> {
+        let Some(years) = self
+            .counts
+            .iter()
+            .filter_map(|count| count.series.years())
+            .reduce(|a, b| a.span(b))
+        else {
+            return Vec::new();
+        };
+        let sum = self
+            .counts
+            .iter()
+            .fold(TimeSeries::zeros(years), |sum, count| {
+                &sum + &count.series.reindex(years)
+            });
+        self.counts
+            .iter()
+            .map(|count| (count.pos, &count.series.reindex(years) / &sum))
+            .collect()
+    }
+}
+
+impl Client {
+    /// Looks up `word` tagged as each part of speech, e.g. `word_NOUN`, and
+    /// fetches the stats of the tagged unigrams.
+    pub async fn pos_profile(&self, word: &str, corpus: Corpus) -> Result<PosProfile, Error> {
+        let options = SearchOptions {
+            max_page_count: 1,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let mut counts = Vec::new();
+        for pos in PartOfSpeech::ALL {
+            let query = Tagged::new(word, Some(pos)).to_string();
+            let mut pages = self.search(query, corpus, options);
+            let Some(page) = pages.next().await.transpose()? else {
+                continue;
+            };
+            let Some(id) = page
+                .ngrams
+                .iter()
+                .find_map(|ngram| match &ngram.tokens[..] {
+                    [token] if token.pos() == Some(pos) && token.text == word => Some(ngram.id),
+                    _ => None,
+                })
+            else {
+                continue;
+            };
+            let Some(ngram) = self.get_ngram(corpus, id).await? else {
+                continue;
+            };
+            counts.push(PosCount {
+                pos,
+                total: ngram.abs_total_match_count,
+                series: ngram.time_series(Measure::Absolute),
+            });
+        }
+        counts.sort_by_key(|count| Reverse(count.total));
+        Ok(PosProfile {
+            word: word.to_string(),
+            counts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartOfSpeech, Tagged};
+    use crate::testing::{ngram, ngram_lite, Fixtures};
+    use crate::{Client, Corpus, NgramStat, NgramTokenKind, Page, QueryTokenKind};
+
+    #[test]
+    fn conversions_between_kinds() {
+        for pos in PartOfSpeech::ALL {
+            assert_eq!(NgramTokenKind::from(pos).pos(), Some(pos));
+            assert_eq!(QueryTokenKind::from(pos).star_pos(), Some(pos));
+            assert_eq!(pos.tag().parse(), Ok(pos));
+        }
+        assert_eq!(NgramTokenKind::Term.pos(), None);
+        assert_eq!(QueryTokenKind::Star.star_pos(), None);
+        assert!("noun".parse::<PartOfSpeech>().is_err());
+    }
+
+    #[test]
+    fn parse_and_format_tagged_words() {
+        let tagged = Tagged::parse("run_VERB");
+        assert_eq!(tagged, Tagged::new("run", Some(PartOfSpeech::Verb)));
+        assert_eq!(tagged.to_string(), "run_VERB");
+        assert_eq!(
+            Tagged::parse("*_NOUN"),
+            Tagged::new("*", Some(PartOfSpeech::Noun))
+        );
+        assert_eq!(Tagged::parse("_END_"), Tagged::new("_END_", None));
+        assert_eq!(Tagged::parse("_NOUN"), Tagged::new("_NOUN", None));
+        assert_eq!(Tagged::parse("snake_case"), Tagged::new("snake_case", None));
+        assert_eq!(Tagged::new("*", None).to_string(), "*");
+    }
+
+    #[tokio::test]
+    async fn profile_of_noun_and_verb() {
+        let mut fixtures = Fixtures::default();
+        for (pos, stats) in [
+            (
+                PartOfSpeech::Noun,
+                vec![NgramStat::new(1900, 1, 0.0), NgramStat::new(1901, 3, 0.0)],
+            ),
+            (
+                PartOfSpeech::Verb,
+                vec![NgramStat::new(1901, 1, 0.0), NgramStat::new(1902, 5, 0.0)],
+            ),
+        ] {
+            let id = Tagged::new("run", Some(pos)).to_string();
+            let mut lite = ngram_lite(&["run"], 0);
+            lite.id = id.clone();
+            lite.tokens[0].kind = pos.into();
+            let mut ngram = ngram(stats);
+            ngram.id = id.clone();
+            fixtures = fixtures.ngram(&ngram).search(
+                &id,
+                &Page {
+                    query_tokens: Vec::new(),
+                    ngrams: vec![lite],
+                },
+            );
+        }
+        let client = Client::with_transport(fixtures);
+        let profile = client.pos_profile("run", Corpus::English).await.unwrap();
+
+        let pos: Vec<_> = profile.counts.iter().map(|count| count.pos).collect();
+        assert_eq!(pos, [PartOfSpeech::Verb, PartOfSpeech::Noun]);
+        assert_eq!(profile.total(), 10);
+        assert_eq!(profile.share(PartOfSpeech::Noun), 0.4);
+        assert_eq!(profile.share(PartOfSpeech::Adj), 0.0);
+        let shares = profile.shares_by_year();
+        assert_eq!(shares[0].1.values(), [0.0, 0.25, 1.0]);
+        assert_eq!(shares[1].1.values(), [1.0, 0.75, 0.0]);
+    }
+}
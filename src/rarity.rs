@@ -0,0 +1,344 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Frequency profile of all ngrams of a text to spot unusual phrasing.
+//!
+//! The text is split into tokens like the server splits query terms:
+//! punctuation marks and hyphens are tokens of their own, and English
+//! contractions are split as in the corpus, e.g. `don't` becomes `do n't`.
+//! Every span of 1 to 5 tokens within a sentence is looked up, several spans
+//! per request, and the counts are cached by the [`RarityProfiler`] across
+//! texts.
+
+use crate::{Client, Corpus, Error, NgramLength, TotalCounts};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+pub(crate) const DEFAULT_BATCH_SIZE: usize = 20;
+
+/// Suffixes split off English words, the longest first.
+const ENGLISH_CONTRACTIONS: [&str; 7] = ["n't", "'ll", "'re", "'ve", "'s", "'d", "'m"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    /// Byte range in the tokenized text.
+    pub range: Range<usize>,
+}
+
+/// Splits `text` into tokens as described in the [module documentation](self).
+pub fn tokenize(text: &str, corpus: Corpus) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        if c.is_alphanumeric() {
+            let mut prev = c;
+            while let Some(&(i, c)) = chars.peek() {
+                let next = text[i + c.len_utf8()..].chars().next();
+                let inner = match c {
+                    '\'' | '’' => prev.is_alphabetic() && next.is_some_and(char::is_alphabetic),
+                    '.' | ',' => prev.is_numeric() && next.is_some_and(char::is_numeric),
+                    _ => c.is_alphanumeric(),
+                };
+                if !inner {
+                    break;
+                }
+                prev = c;
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            if corpus == Corpus::English {
+                if let Some(split) = contraction(&text[start..end]) {
+                    push(&mut tokens, text, start..start + split);
+                    push(&mut tokens, text, start + split..end);
+                    continue;
+                }
+            }
+        } else if c == '.' {
+            while let Some(&(i, '.')) = chars.peek() {
+                end = i + 1;
+                chars.next();
+            }
+        }
+        push(&mut tokens, text, start..end);
+    }
+    tokens
+}
+
+fn push(tokens: &mut Vec<Token>, text: &str, range: Range<usize>) {
+    tokens.push(Token {
+        text: text[range.clone()].to_string(),
+        range,
+    });
+}
+
+/// Byte offset of the contraction suffix of `word`, if any.
+fn contraction(word: &str) -> Option<usize> {
+    ENGLISH_CONTRACTIONS.iter().find_map(|suffix| {
+        let (split, _) = word.char_indices().rev().nth(suffix.len() - 1)?;
+        let tail = word[split..].replace('’', "'").to_lowercase();
+        (split > 0 && tail == *suffix).then_some(split)
+    })
+}
+
+/// Ranges of up to `max_len` tokens that do not cross sentence ends, ordered
+/// by their first token and length.
+pub(crate) fn spans(tokens: &[Token], max_len: NgramLength) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut sentence_start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if matches!(token.text.as_str(), "." | "!" | "?") || i + 1 == tokens.len() {
+            for start in sentence_start..=i {
+                for end in start + 1..=(start + max_len.get()).min(i + 1) {
+                    ranges.push(start..end);
+                }
+            }
+            sentence_start = i + 1;
+        }
+    }
+    ranges
+}
+
+/// Texts of `tokens` separated by a space, as used in queries.
+pub(crate) fn span_text(tokens: &[Token]) -> String {
+    let texts: Vec<_> = tokens.iter().map(|token| token.text.as_str()).collect();
+    texts.join(" ")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// Range of tokens of the [`Profile`].
+    pub tokens: Range<usize>,
+    /// Byte range in the profiled text.
+    pub bytes: Range<usize>,
+    /// Texts of the tokens separated by a space.
+    pub text: String,
+    /// Corresponds to `abs_total_match_count`, zero if not attested.
+    pub count: u64,
+    /// `count` divided by the total match count of all ngrams of the same
+    /// length.
+    pub relative_frequency: f64,
+    /// Negative decimal logarithm of the relative frequency, with half a match
+    /// assumed for unattested spans. Comparable between spans of the same
+    /// length only.
+    pub rarity: f64,
+}
+
+impl Span {
+    pub fn len(&self) -> NgramLength {
+        NgramLength::new(self.tokens.len()).unwrap()
+    }
+
+    pub fn is_attested(&self) -> bool {
+        self.count > 0
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub tokens: Vec<Token>,
+    /// All spans ordered by their first token and length.
+    pub spans: Vec<Span>,
+}
+
+impl Profile {
+    /// Spans of length `len`, rarest first.
+    pub fn rarest(&self, len: NgramLength) -> Vec<&Span> {
+        let mut spans: Vec<_> = self.spans.iter().filter(|span| span.len() == len).collect();
+        spans.sort_by(|a, b| b.rarity.total_cmp(&a.rarity));
+        spans
+    }
+
+    /// Unattested spans whose shorter subspans are all attested, i.e. the
+    /// smallest pieces of phrasing not found in the corpus.
+    pub fn unattested(&self) -> Vec<&Span> {
+        let attested: HashSet<_> = self
+            .spans
+            .iter()
+            .filter(|span| span.is_attested())
+            .map(|span| span.tokens.clone())
+            .collect();
+        self.spans
+            .iter()
+            .filter(|span| {
+                let Range { start, end } = span.tokens;
+                !span.is_attested()
+                    && (end - start == 1
+                        || (attested.contains(&(start..end - 1))
+                            && attested.contains(&(start + 1..end))))
+            })
+            .collect()
+    }
+}
+
+/// Profiles texts and caches the counts of their spans.
+#[derive(Clone)]
+pub struct RarityProfiler {
+    client: Client,
+    corpus: Corpus,
+    batch_size: usize,
+    cache: HashMap<String, u64>,
+}
+
+impl RarityProfiler {
+    pub fn new(client: Client, corpus: Corpus) -> Self {
+        Self {
+            client,
+            corpus,
+            batch_size: DEFAULT_BATCH_SIZE,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Maximum number of spans looked up with one alternation query. Spans
+    /// containing query operators are always looked up on their own.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Number of distinct spans with a cached count.
+    pub fn cache_len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    pub async fn profile(&mut self, text: &str, totals: &TotalCounts) -> Result<Profile, Error> {
+        let tokens = tokenize(text, self.corpus);
+        let ranges = spans(&tokens, NgramLength::MAX);
+        let texts: Vec<String> = ranges
+            .iter()
+            .map(|range| span_text(&tokens[range.clone()]))
+            .collect();
+
+        let mut missing: Vec<String> = texts
+            .iter()
+            .filter(|text| !self.cache.contains_key(*text))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        missing.sort();
+        self.client
+            .record_cache(texts.len() - missing.len(), missing.len());
+        for batch in missing.chunks(self.batch_size) {
+            let found = self.client.find_phrases(self.corpus, batch, true).await?;
+            for text in batch {
+                let count = found
+                    .get(text)
+                    .map_or(0, |ngram| ngram.abs_total_match_count);
+                self.cache.insert(text.clone(), count);
+            }
+        }
+
+        let spans = ranges
+            .into_iter()
+            .zip(texts)
+            .map(|(range, text)| {
+                let count = self.cache[&text];
+                let len = NgramLength::new(range.len()).unwrap();
+                let total: u64 = totals.counts(len).map(|(_, count)| count).sum();
+                let frequency = |count: f64| {
+                    if total == 0 {
+                        0.0
+                    } else {
+                        count / total as f64
+                    }
+                };
+                let rarity = match frequency(count as f64 + 0.5) {
+                    0.0 => 0.0,
+                    frequency => -frequency.log10(),
+                };
+                Span {
+                    bytes: tokens[range.start].range.start..tokens[range.end - 1].range.end,
+                    tokens: range,
+                    text,
+                    count,
+                    relative_frequency: frequency(count as f64),
+                    rarity,
+                }
+            })
+            .collect();
+        Ok(Profile { tokens, spans })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, RarityProfiler};
+    use crate::testing::{ngram_lite, total_counts, Fixtures};
+    use crate::{Client, Corpus, NgramLength, Page};
+
+    fn texts(text: &str, corpus: Corpus) -> Vec<String> {
+        tokenize(text, corpus)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_like_the_server() {
+        assert_eq!(
+            texts(
+                "I don't know, it’s well-known... Costs: 3.5%!",
+                Corpus::English
+            ),
+            [
+                "I", "do", "n't", "know", ",", "it", "’s", "well", "-", "known", "...", "Costs",
+                ":", "3.5", "%", "!"
+            ]
+        );
+        assert_eq!(texts("Wie geht's?", Corpus::German), ["Wie", "geht's", "?"]);
+        let tokens = tokenize("über alles", Corpus::German);
+        assert_eq!(tokens[1].range, 6..11);
+    }
+
+    #[tokio::test]
+    async fn profile_flags_unattested_phrasing() {
+        let mut fixtures = Fixtures::default();
+        for (words, count) in [
+            (&["cup"][..], 100),
+            (&["of"], 1000),
+            (&["tea"], 50),
+            (&["cup", "of"], 20),
+            (&["of", "tea"], 10),
+        ] {
+            let page = Page {
+                query_tokens: Vec::new(),
+                ngrams: vec![ngram_lite(words, count)],
+            };
+            fixtures = fixtures.search(&words.join(" "), &page);
+        }
+        let client = Client::with_transport(fixtures);
+        let mut profiler = RarityProfiler::new(client.clone(), Corpus::English).with_batch_size(1);
+        let totals = total_counts(1900, &[1000]);
+
+        let profile = profiler.profile("cup of tea", &totals).await.unwrap();
+        assert_eq!(profile.spans.len(), 6);
+        assert_eq!(profiler.cache_len(), 6);
+        let unattested: Vec<_> = profile
+            .unattested()
+            .iter()
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(unattested, ["cup of tea"]);
+        let rarest = profile.rarest(NgramLength::new(1).unwrap());
+        assert_eq!(rarest[0].text, "tea");
+        assert_eq!(rarest[0].relative_frequency, 0.05);
+        assert_eq!(rarest[0].bytes, 7..10);
+
+        let profile = profiler.profile("a cup", &totals).await.unwrap();
+        assert_eq!(profile.spans.len(), 3);
+        assert_eq!(profiler.cache_len(), 8);
+        let metrics = client.metrics();
+        assert_eq!((metrics.cache_hits, metrics.cache_misses), (1, 8));
+    }
+}
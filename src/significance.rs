@@ -0,0 +1,314 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Significance tests for frequency differences between periods or corpora.
+
+use crate::frequency::YearRangeError;
+use crate::{Ngram, TotalCounts, YearRange};
+
+/// Matches of an ngram and the total matches of all ngrams of the same length
+/// in one period of one corpus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub matches: u64,
+    pub total: u64,
+}
+
+impl Sample {
+    /// Panics if `matches` exceeds `total`.
+    pub fn new(matches: u64, total: u64) -> Self {
+        assert!(matches <= total, "matches must not exceed total");
+        Self { matches, total }
+    }
+
+    /// Matches of `ngram` within `years`, with the totals of its length as
+    /// denominator. Panics if the ngram does not have 1 to 5 tokens.
+    pub fn from_ngram(
+        ngram: &Ngram,
+        totals: &TotalCounts,
+        years: YearRange,
+    ) -> Result<Self, YearRangeError> {
+        let total = totals.total_in(ngram.expect_len(), years)?;
+        Ok(Self::new(ngram.match_count_in(years).min(total), total))
+    }
+
+    pub fn relative_frequency(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.matches as f64 / self.total as f64
+        }
+    }
+
+    /// Wilson score interval of the relative frequency at `confidence`, e.g.
+    /// 0.95. Panics if `confidence` is not in `(0, 1)`.
+    pub fn confidence_interval(&self, confidence: f64) -> (f64, f64) {
+        assert!(
+            confidence > 0.0 && confidence < 1.0,
+            "confidence must be in (0, 1)"
+        );
+        if self.total == 0 {
+            return (0.0, 1.0);
+        }
+        let z = normal_quantile(1.0 - (1.0 - confidence) / 2.0);
+        let n = self.total as f64;
+        let p = self.relative_frequency();
+        let z2 = z * z;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let half_width = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        (
+            (center - half_width).max(0.0),
+            (center + half_width).min(1.0),
+        )
+    }
+}
+
+/// Result of comparing the relative frequencies of two samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    pub a: Sample,
+    pub b: Sample,
+    /// Log-likelihood ratio statistic G² of the 2x2 contingency table.
+    pub log_likelihood: f64,
+    /// Probability of a G² at least as large if both samples had the same
+    /// relative frequency (chi-square distribution, one degree of freedom).
+    pub p_value: f64,
+    /// Pearson's chi-square statistic of the 2x2 contingency table.
+    pub chi_square: f64,
+    pub chi_square_p_value: f64,
+    /// Effect size as binary log of the ratio of relative frequencies `a / b`.
+    /// Zero matches are replaced by 0.5 to keep the ratio finite, so 1 means
+    /// twice as frequent in `a` and -1 half as frequent.
+    pub log_ratio: f64,
+    /// Confidence intervals of the relative frequencies of `a` and `b`.
+    pub a_interval: (f64, f64),
+    pub b_interval: (f64, f64),
+}
+
+impl Comparison {
+    /// Compares `a` with `b`, with confidence intervals at `confidence`.
+    /// Panics if `confidence` is not in `(0, 1)`.
+    pub fn new(a: Sample, b: Sample, confidence: f64) -> Self {
+        let observed = [
+            [a.matches as f64, (a.total - a.matches) as f64],
+            [b.matches as f64, (b.total - b.matches) as f64],
+        ];
+        let n = (a.total + b.total) as f64;
+        let mut log_likelihood = 0.0;
+        let mut chi_square = 0.0;
+        for row in &observed {
+            for (j, &o) in row.iter().enumerate() {
+                let row_sum = row[0] + row[1];
+                let col_sum = observed[0][j] + observed[1][j];
+                let e = row_sum * col_sum / n;
+                if e > 0.0 {
+                    chi_square += (o - e).powi(2) / e;
+                }
+                if o > 0.0 {
+                    log_likelihood += o * (o / e).ln();
+                }
+            }
+        }
+        let log_likelihood = 2.0 * log_likelihood;
+        Self {
+            a,
+            b,
+            log_likelihood,
+            p_value: chi_square_p_value(log_likelihood),
+            chi_square,
+            chi_square_p_value: chi_square_p_value(chi_square),
+            log_ratio: log_ratio(a, b),
+            a_interval: a.confidence_interval(confidence),
+            b_interval: b.confidence_interval(confidence),
+        }
+    }
+
+    /// Whether the G² test rejects equal frequencies at significance `alpha`.
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+impl Ngram {
+    /// Compares the relative frequency of this ngram within `a` and `b` with
+    /// 95% confidence intervals. Panics if the ngram does not have 1 to 5
+    /// tokens.
+    pub fn compare_periods(
+        &self,
+        totals: &TotalCounts,
+        a: YearRange,
+        b: YearRange,
+    ) -> Result<Comparison, YearRangeError> {
+        Ok(Comparison::new(
+            Sample::from_ngram(self, totals, a)?,
+            Sample::from_ngram(self, totals, b)?,
+            0.95,
+        ))
+    }
+}
+
+fn log_ratio(a: Sample, b: Sample) -> f64 {
+    if a.total == 0 || b.total == 0 {
+        return 0.0;
+    }
+    let adjusted = |sample: Sample| {
+        let matches = if sample.matches == 0 {
+            0.5
+        } else {
+            sample.matches as f64
+        };
+        matches / sample.total as f64
+    };
+    (adjusted(a) / adjusted(b)).log2()
+}
+
+/// Upper tail of the chi-square distribution with one degree of freedom.
+fn chi_square_p_value(statistic: f64) -> f64 {
+    if statistic <= 0.0 {
+        1.0
+    } else {
+        erfc((statistic / 2.0).sqrt())
+    }
+}
+
+/// Complementary error function with a fractional error below 1.2e-7
+/// (Numerical Recipes, `erfcc`).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+/// Quantile function of the standard normal distribution (Acklam's rational
+/// approximation, relative error below 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const LOW: f64 = 0.024_25;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{erfc, normal_quantile, Comparison, Sample};
+    use crate::testing::{ngram, tokens, total_counts};
+    use crate::{NgramStat, YearRange};
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn distribution_functions() {
+        assert_close(erfc(0.0), 1.0, 1e-7);
+        assert_close(erfc(1.0), 0.157_299_207, 1e-7);
+        assert_close(erfc(-1.0), 1.842_700_793, 1e-7);
+        assert_close(normal_quantile(0.975), 1.959_963_985, 1e-8);
+        assert_close(normal_quantile(0.01), -2.326_347_874, 1e-8);
+    }
+
+    #[test]
+    fn compare_samples() {
+        let a = Sample::new(30, 10_000);
+        let b = Sample::new(10, 10_000);
+        let comparison = Comparison::new(a, b, 0.95);
+        assert_close(comparison.chi_square, 10.020_04, 1e-4);
+        assert_close(comparison.chi_square_p_value, 0.001_548_5, 1e-6);
+        assert_close(comparison.log_likelihood, 10.485_0, 1e-3);
+        assert!(comparison.is_significant(0.01));
+        assert_close(comparison.log_ratio, 3f64.log2(), 1e-12);
+        assert_close(comparison.a_interval.0, 0.002_102, 1e-5);
+        assert_close(comparison.a_interval.1, 0.004_279, 1e-5);
+
+        let same = Comparison::new(a, a, 0.95);
+        assert_close(same.p_value, 1.0, 1e-12);
+        assert_eq!(same.log_ratio, 0.0);
+    }
+
+    #[test]
+    fn compare_periods() {
+        let totals = total_counts(1900, &[1000, 1000, 1000, 1000]);
+        let mut ngram = ngram(vec![
+            NgramStat::new(1900, 1, 0.0),
+            NgramStat::new(1902, 40, 0.0),
+            NgramStat::new(1903, 40, 0.0),
+        ]);
+        ngram.tokens = tokens(&["x"]);
+        let comparison = ngram
+            .compare_periods(
+                &totals,
+                YearRange::new(1902, 1903),
+                YearRange::new(1900, 1901),
+            )
+            .unwrap();
+        assert_eq!(comparison.a, Sample::new(80, 2000));
+        assert_eq!(comparison.b, Sample::new(1, 2000));
+        assert!(comparison.log_ratio > 6.0);
+        assert!(comparison.p_value < 1e-10);
+        assert!(ngram
+            .compare_periods(
+                &totals,
+                YearRange::new(1800, 1801),
+                YearRange::new(1900, 1901)
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Smoothing and normalization of [`TimeSeries`].
+
+use crate::{NgramLength, TimeSeries, TotalCounts};
+
+impl TimeSeries {
+    /// Centered moving average as computed by the Google Books Ngram Viewer.
+    ///
+    /// The value for a year is the mean of the raw values from `smoothing`
+    /// years before to `smoothing` years after it. Near the ends of the series
+    /// the window is truncated and only the available years are averaged. A
+    /// `smoothing` of zero returns the series unchanged. To reproduce Viewer
+    /// charts, smooth the relative series, not the absolute counts.
+    pub fn moving_average(&self, smoothing: usize) -> TimeSeries {
+        let values = self.values();
+        let mut prefix_sums = Vec::with_capacity(values.len() + 1);
+        prefix_sums.push(0.0);
+        for value in values {
+            prefix_sums.push(prefix_sums.last().unwrap() + value);
+        }
+        let smoothed = (0..values.len())
+            .map(|i| {
+                let start = i.saturating_sub(smoothing);
+                let end = (i + smoothing + 1).min(values.len());
+                (prefix_sums[end] - prefix_sums[start]) / (end - start) as f64
+            })
+            .collect();
+        self.with_values(smoothed)
+    }
+
+    /// Simple exponential smoothing `s[t] = alpha * x[t] + (1 - alpha) * s[t-1]`
+    /// with `s[0] = x[0]`. Panics if `alpha` is not in `(0, 1]`.
+    pub fn exponential_smoothing(&self, alpha: f64) -> TimeSeries {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in (0, 1]");
+        let mut state = None;
+        self.map(|value| {
+            let next = match state {
+                Some(prev) => alpha * value + (1.0 - alpha) * prev,
+                None => value,
+            };
+            state = Some(next);
+            next
+        })
+    }
+
+    /// Locally weighted linear regression (LOESS) with tricube weights.
+    ///
+    /// `span` is the fraction of years used for each local fit and is clamped
+    /// so that every fit uses at least two years. Panics if `span` is not in
+    /// `(0, 1]`.
+    pub fn loess(&self, span: f64) -> TimeSeries {
+        assert!(span > 0.0 && span <= 1.0, "span must be in (0, 1]");
+        let values = self.values();
+        let n = values.len();
+        if n < 2 {
+            return self.clone();
+        }
+        let q = ((span * n as f64).ceil() as usize).clamp(2, n);
+        let smoothed = (0..n)
+            .map(|i| {
+                let mut distances: Vec<f64> = (0..n).map(|j| i.abs_diff(j) as f64).collect();
+                distances.sort_unstable_by(f64::total_cmp);
+                // Slightly widen the bandwidth so that the q-th nearest year
+                // still gets a (small) positive weight.
+                let bandwidth = distances[q - 1] * 1.000_001 + f64::EPSILON;
+                let (mut sw, mut swx, mut swy, mut swxx, mut swxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+                for (j, &y) in values.iter().enumerate() {
+                    let x = j as f64 - i as f64;
+                    let d = x.abs() / bandwidth;
+                    if d >= 1.0 {
+                        continue;
+                    }
+                    let w = (1.0 - d.powi(3)).powi(3);
+                    sw += w;
+                    swx += w * x;
+                    swy += w * y;
+                    swxx += w * x * x;
+                    swxy += w * x * y;
+                }
+                let denominator = sw * swxx - swx * swx;
+                if denominator.abs() < f64::EPSILON {
+                    swy / sw
+                } else {
+                    // Intercept of the local fit, since x is centered at year i.
+                    let slope = (sw * swxy - swx * swy) / denominator;
+                    (swy - slope * swx) / sw
+                }
+            })
+            .collect();
+        self.with_values(smoothed)
+    }
+
+    /// Divides each value by the total match count of all ngrams of length
+    /// `len` in the same year. Applied to an absolute series this
+    /// yields the relative frequencies also found in `NgramStat::rel_match_count`.
+    pub fn relative_to_total(&self, totals: &TotalCounts, len: NgramLength) -> TimeSeries {
+        self / &totals.series(len)
+    }
+
+    /// Standardizes the series to zero mean and unit (population) standard
+    /// deviation. A constant series becomes all zeros.
+    pub fn z_score(&self) -> TimeSeries {
+        if self.is_empty() {
+            return TimeSeries::default();
+        }
+        let n = self.len() as f64;
+        let mean = self.sum() / n;
+        let variance = self
+            .values()
+            .iter()
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let std_dev = variance.sqrt();
+        if std_dev == 0.0 {
+            return self.map(|_| 0.0);
+        }
+        self.map(|value| (value - mean) / std_dev)
+    }
+
+    /// Divides each value by the maximum value, so that the peak becomes one.
+    /// A series whose maximum is not positive becomes all zeros.
+    pub fn share_of_peak(&self) -> TimeSeries {
+        let peak = self.values().iter().copied().fold(f64::MIN, f64::max);
+        if peak <= 0.0 {
+            return self.map(|_| 0.0);
+        }
+        self / peak
+    }
+
+    fn with_values(&self, values: Vec<f64>) -> TimeSeries {
+        match self.years() {
+            Some(years) => TimeSeries::new(years.first(), values),
+            None => TimeSeries::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::total_counts;
+    use crate::{NgramLength, TimeSeries};
+
+    fn assert_close(actual: &TimeSeries, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.values().iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn moving_average_truncates_window_at_edges() {
+        let series = TimeSeries::new(1900, vec![1.0, 2.0, 3.0, 4.0, 10.0]);
+        assert_eq!(series.moving_average(0), series);
+        assert_close(&series.moving_average(1), &[1.5, 2.0, 3.0, 17.0 / 3.0, 7.0]);
+        assert_close(&series.moving_average(3), &[2.5, 4.0, 4.0, 4.0, 4.75]);
+        assert_eq!(series.moving_average(1).years(), series.years());
+    }
+
+    #[test]
+    fn exponential_smoothing() {
+        let series = TimeSeries::new(1900, vec![2.0, 4.0, 4.0]);
+        assert_close(&series.exponential_smoothing(0.5), &[2.0, 3.0, 3.5]);
+        assert_eq!(series.exponential_smoothing(1.0), series);
+    }
+
+    #[test]
+    fn loess_reproduces_linear_trend() {
+        let series = TimeSeries::new(1900, (0..20).map(|x| 3.0 + 0.5 * x as f64).collect());
+        assert_close(&series.loess(0.3), series.values());
+        let noisy = TimeSeries::new(1900, vec![1.0, 5.0, 1.0, 5.0, 1.0, 5.0, 1.0]);
+        let smoothed = noisy.loess(1.0);
+        assert!(smoothed.values().iter().all(|v| (1.5..4.5).contains(v)));
+    }
+
+    #[test]
+    fn normalizations() {
+        let counts = TimeSeries::new(1900, vec![1.0, 2.0, 3.0]);
+        let totals = total_counts(1900, &[10, 0, 30]);
+        assert_close(
+            &counts.relative_to_total(&totals, NgramLength::MIN),
+            &[0.1, 0.0, 0.1],
+        );
+
+        let z = counts.z_score();
+        assert_close(&z, &[-(1.5f64.sqrt()), 0.0, 1.5f64.sqrt()]);
+        assert_close(
+            &TimeSeries::new(1900, vec![2.0, 2.0]).z_score(),
+            &[0.0, 0.0],
+        );
+
+        assert_close(&counts.share_of_peak(), &[1.0 / 3.0, 2.0 / 3.0, 1.0]);
+    }
+}
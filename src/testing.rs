@@ -0,0 +1,178 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Helpers shared by unit tests.
+
+#[cfg(any(feature = "blocking", feature = "tower"))]
+use crate::middleware::Middleware;
+use crate::middleware::{Request, Response};
+use crate::transport::{BoxFuture, Transport};
+use crate::{
+    Error, Ngram, NgramLite, NgramStat, NgramToken, NgramTokenKind, Page, QueryToken,
+    QueryTokenKind, TotalCounts, TotalCountsByYear, TOTAL_COUNTS_BY_YEAR_LEN,
+};
+use std::collections::HashMap;
+
+/// Ngram without tokens whose total match count is the sum of `stats`.
+pub(crate) fn ngram(stats: Vec<NgramStat>) -> Ngram {
+    Ngram {
+        id: String::new(),
+        abs_total_match_count: stats.iter().map(|stat| stat.abs_match_count).sum(),
+        rel_total_match_count: 0.0,
+        tokens: Vec::new(),
+        stats,
+    }
+}
+
+/// Untagged term tokens, or sentence boundary tokens for `_START_` and
+/// `_END_`.
+pub(crate) fn tokens(words: &[&str]) -> Vec<NgramToken> {
+    words
+        .iter()
+        .map(|word| NgramToken {
+            kind: match *word {
+                "_START_" => NgramTokenKind::SentenceStart,
+                "_END_" => NgramTokenKind::SentenceEnd,
+                _ => NgramTokenKind::Term,
+            },
+            text: word.to_string(),
+            inserted: false,
+            completed: false,
+        })
+        .collect()
+}
+
+/// Ngram lite of untagged `words` with `count` matches.
+pub(crate) fn ngram_lite(words: &[&str], count: u64) -> NgramLite {
+    NgramLite {
+        id: words.join("_"),
+        abs_total_match_count: count,
+        rel_total_match_count: 0.0,
+        tokens: tokens(words),
+        r#abstract: false,
+    }
+}
+
+/// Search result page of [`ngram_lite`]s without query tokens.
+pub(crate) fn page(ngrams: &[(&[&str], u64)]) -> Page {
+    Page {
+        query_tokens: Vec::new(),
+        ngrams: ngrams
+            .iter()
+            .map(|(words, count)| ngram_lite(words, *count))
+            .collect(),
+    }
+}
+
+pub(crate) fn query_tokens(tokens: &[(QueryTokenKind, &str)]) -> Vec<QueryToken> {
+    tokens
+        .iter()
+        .map(|&(kind, text)| QueryToken {
+            kind,
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+/// Total counts starting at `min_year` with the same `counts` for every ngram
+/// length and zeros for all following years.
+pub(crate) fn total_counts(min_year: u16, counts: &[u64]) -> TotalCounts {
+    let mut by_year = [0; TOTAL_COUNTS_BY_YEAR_LEN];
+    by_year[..counts.len()].copy_from_slice(counts);
+    TotalCounts {
+        min_year,
+        max_year: min_year + (TOTAL_COUNTS_BY_YEAR_LEN - 1) as u16,
+        match_counts: std::array::from_fn(|_| TotalCountsByYear(by_year)),
+    }
+}
+
+/// Transport answering requests from canned ngrams and search results
+/// instead of the network, so that tests build without the reqwest
+/// transport. Unknown searches find nothing, unknown ngrams get a 404, and
+/// queries without a term are rejected like the server does.
+#[derive(Default)]
+pub(crate) struct Fixtures {
+    ngrams: HashMap<String, String>,
+    searches: HashMap<String, String>,
+}
+
+impl Fixtures {
+    pub(crate) fn ngram(mut self, ngram: &Ngram) -> Self {
+        let body = serde_json::to_string(ngram).unwrap();
+        self.ngrams.insert(ngram.id.clone(), body);
+        self
+    }
+
+    /// Full stats of ngrams of untagged words, and a single result page of
+    /// them for `query`.
+    pub(crate) fn found(mut self, query: &str, ngrams: &[(&[&str], Vec<NgramStat>)]) -> Self {
+        let counts: Vec<_> = ngrams
+            .iter()
+            .map(|(words, stats)| (*words, stats.iter().map(|stat| stat.abs_match_count).sum()))
+            .collect();
+        let page = page(&counts);
+        for (lite, (_, stats)) in page.ngrams.iter().zip(ngrams) {
+            let mut ngram = ngram(stats.clone());
+            ngram.id = lite.id.clone();
+            ngram.tokens = lite.tokens.clone();
+            self = self.ngram(&ngram);
+        }
+        self.search(query, &page)
+    }
+
+    /// Single result page for `query`.
+    pub(crate) fn search(mut self, query: &str, page: &Page) -> Self {
+        let body = serde_json::to_string(page).unwrap();
+        self.searches.insert(query.to_string(), body);
+        self
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        if request.resource == "search" {
+            let query = request
+                .query
+                .iter()
+                .find(|(key, _)| key == "query")
+                .map_or("", |(_, query)| query.as_str());
+            if query.split_whitespace().all(|term| term == "*") {
+                let body = r#"{"error":{"code":"INVALID_QUERY.NO_TERM"}}"#;
+                return Response::new(400, body);
+            }
+            let body = self
+                .searches
+                .get(query)
+                .map_or(r#"{"queryTokens":[],"ngrams":[]}"#, String::as_str);
+            return Response::new(200, body);
+        }
+        match self.ngrams.get(&request.resource) {
+            Some(body) => Response::new(200, body.as_str()),
+            None => Response::new(404, ""),
+        }
+    }
+}
+
+impl Transport for Fixtures {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        let response = self.respond(&request);
+        Box::pin(async { Ok(response) })
+    }
+}
+
+/// Middleware answering every search with an empty page for the query `hello`
+/// that always has a next page, and everything else with a 404.
+#[cfg(any(feature = "blocking", feature = "tower"))]
+pub(crate) struct Canned;
+
+#[cfg(any(feature = "blocking", feature = "tower"))]
+impl Middleware for Canned {
+    fn before_request(&self, request: &mut Request) -> Option<Response> {
+        match request.resource.as_str() {
+            "search" => Some(Response::new(
+                200,
+                r#"{"queryTokens":[{"kind":"TERM","text":"hello"}],"ngrams":[],"nextPageToken":"abc"}"#,
+            )),
+            _ => Some(Response::new(404, "")),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Rendering of ngram tokens as text.
+//!
+//! Tokens are joined with spaces, except around punctuation, brackets,
+//! quotes, hyphens and English contractions, following the typographic
+//! conventions of the corpus language. For example, the English tokens
+//! `I`, `do`, `n't`, `know`, `.` become `I don't know.`.
+
+use crate::pos::Tagged;
+use crate::{Corpus, Ngram, NgramLite, NgramToken, NgramTokenKind};
+use std::borrow::Cow;
+
+/// How the `_START_` and `_END_` sentence boundary tokens are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SentenceTags {
+    #[default]
+    Drop,
+    Keep,
+}
+
+/// Property of a token that a [`Highlighter`] can mark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+    /// Token matched by `**` in the query.
+    Inserted,
+    /// Token completed from a prefix in the query.
+    Completed,
+}
+
+/// Markup around inserted and completed tokens.
+pub trait Highlighter {
+    fn open(&self, mark: Mark) -> &str;
+
+    fn close(&self, mark: Mark) -> &str;
+
+    /// Escapes the text of every token, e.g. `<` for HTML.
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Terminal escape codes: inserted tokens are dimmed, completed tokens are
+/// underlined.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ansi;
+
+impl Highlighter for Ansi {
+    fn open(&self, mark: Mark) -> &str {
+        match mark {
+            Mark::Inserted => "\x1b[2m",
+            Mark::Completed => "\x1b[4m",
+        }
+    }
+
+    fn close(&self, _: Mark) -> &str {
+        "\x1b[0m"
+    }
+}
+
+/// `<span>` elements with the class `inserted` or `completed`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Html;
+
+impl Highlighter for Html {
+    fn open(&self, mark: Mark) -> &str {
+        match mark {
+            Mark::Inserted => r#"<span class="inserted">"#,
+            Mark::Completed => r#"<span class="completed">"#,
+        }
+    }
+
+    fn close(&self, _: Mark) -> &str {
+        "</span>"
+    }
+
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        escape(text, |c| match c {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '"' => Some("&quot;"),
+            '\'' => Some("&#39;"),
+            _ => None,
+        })
+    }
+}
+
+/// Inserted tokens in italics, completed tokens in bold.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Markdown;
+
+impl Highlighter for Markdown {
+    fn open(&self, mark: Mark) -> &str {
+        match mark {
+            Mark::Inserted => "_",
+            Mark::Completed => "**",
+        }
+    }
+
+    fn close(&self, mark: Mark) -> &str {
+        self.open(mark)
+    }
+
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        escape(text, |c| match c {
+            '\\' => Some("\\\\"),
+            '*' => Some("\\*"),
+            '_' => Some("\\_"),
+            '`' => Some("\\`"),
+            '[' => Some("\\["),
+            ']' => Some("\\]"),
+            _ => None,
+        })
+    }
+}
+
+fn escape<'a>(text: &'a str, replace: impl Fn(char) -> Option<&'static str>) -> Cow<'a, str> {
+    if !text.chars().any(|c| replace(c).is_some()) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match replace(c) {
+            Some(replacement) => escaped.push_str(replacement),
+            None => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[derive(Clone, Copy)]
+pub struct TextOptions<'a> {
+    /// Language whose punctuation spacing is used.
+    pub corpus: Corpus,
+    /// Appends tags like `_NOUN` to tagged tokens.
+    pub pos_suffixes: bool,
+    pub sentence_tags: SentenceTags,
+    pub highlighter: Option<&'a dyn Highlighter>,
+}
+
+impl TextOptions<'_> {
+    pub fn new(corpus: Corpus) -> Self {
+        Self {
+            corpus,
+            pos_suffixes: false,
+            sentence_tags: SentenceTags::default(),
+            highlighter: None,
+        }
+    }
+}
+
+impl Ngram {
+    pub fn to_text(&self, options: &TextOptions) -> String {
+        detokenize(&self.tokens, options)
+    }
+}
+
+impl NgramLite {
+    pub fn to_text(&self, options: &TextOptions) -> String {
+        detokenize(&self.tokens, options)
+    }
+}
+
+/// Joins `tokens` as described in the [module documentation](self).
+pub fn detokenize(tokens: &[NgramToken], options: &TextOptions) -> String {
+    let mut text = String::new();
+    let mut glue_next = true;
+    let mut open_quote = false;
+    for token in tokens {
+        let is_sentence_tag = matches!(
+            token.kind,
+            NgramTokenKind::SentenceStart | NgramTokenKind::SentenceEnd
+        );
+        if is_sentence_tag && options.sentence_tags == SentenceTags::Drop {
+            continue;
+        }
+        let spacing = if is_sentence_tag {
+            Spacing::Free
+        } else {
+            spacing(&token.text, options.corpus, &mut open_quote)
+        };
+        if !glue_next && !matches!(spacing, Spacing::GluePrev | Spacing::GlueBoth) {
+            text.push(' ');
+        }
+        glue_next = matches!(spacing, Spacing::GlueNext | Spacing::GlueBoth);
+
+        let word = match options.highlighter {
+            Some(highlighter) => highlighter.escape(&token.text),
+            None => Cow::Borrowed(token.text.as_str()),
+        };
+        let pos = token.pos().filter(|_| options.pos_suffixes);
+        let mark = if token.completed {
+            Some(Mark::Completed)
+        } else if token.inserted {
+            Some(Mark::Inserted)
+        } else {
+            None
+        };
+        match (options.highlighter, mark) {
+            (Some(highlighter), Some(mark)) => {
+                text.push_str(highlighter.open(mark));
+                text.push_str(&Tagged::new(&word, pos).to_string());
+                text.push_str(highlighter.close(mark));
+            }
+            _ => text.push_str(&Tagged::new(&word, pos).to_string()),
+        }
+    }
+    text
+}
+
+/// Whether a token attaches to its neighbors.
+enum Spacing {
+    Free,
+    GluePrev,
+    GlueNext,
+    GlueBoth,
+}
+
+fn spacing(token: &str, corpus: Corpus, open_quote: &mut bool) -> Spacing {
+    match token {
+        "," | "." | ";" | ":" | "!" | "?" | "…" | "..." | ")" | "]" | "}" | "%" => {
+            return Spacing::GluePrev
+        }
+        "(" | "[" | "{" => return Spacing::GlueNext,
+        "-" | "/" => return Spacing::GlueBoth,
+        "\"" => {
+            *open_quote = !*open_quote;
+            return if *open_quote {
+                Spacing::GlueNext
+            } else {
+                Spacing::GluePrev
+            };
+        }
+        _ => {}
+    }
+    match corpus {
+        Corpus::English => match token {
+            "“" | "‘" => Spacing::GlueNext,
+            "”" | "’" | "n't" => Spacing::GluePrev,
+            _ if token.starts_with('\'') && token.len() > 1 => Spacing::GluePrev,
+            _ => Spacing::Free,
+        },
+        Corpus::German => match token {
+            "„" | "‚" | "»" => Spacing::GlueNext,
+            "“" | "‘" | "«" => Spacing::GluePrev,
+            _ => Spacing::Free,
+        },
+        Corpus::Russian => match token {
+            "«" | "„" => Spacing::GlueNext,
+            "»" | "“" => Spacing::GluePrev,
+            _ => Spacing::Free,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detokenize, Ansi, Html, Markdown, SentenceTags, TextOptions};
+    use crate::testing::tokens;
+    use crate::{Corpus, NgramTokenKind};
+
+    fn text(words: &[&str], corpus: Corpus) -> String {
+        detokenize(&tokens(words), &TextOptions::new(corpus))
+    }
+
+    #[test]
+    fn punctuation_spacing_by_language() {
+        let english = ["“", "hello", ",", "world", "”", "(", "I", "do", "n't", ")"];
+        assert_eq!(text(&english, Corpus::English), "“hello, world” (I don't)");
+        assert_eq!(
+            text(
+                &["well", "-", "known", "\"", "it", "'s", "\"", "."],
+                Corpus::English
+            ),
+            "well-known \"it's\"."
+        );
+        assert_eq!(
+            text(&["„", "Hallo", "“", ",", "sagte", "er"], Corpus::German),
+            "„Hallo“, sagte er"
+        );
+        assert_eq!(
+            text(
+                &["«", "Привет", "»", "—", "сказал", "он", "."],
+                Corpus::Russian
+            ),
+            "«Привет» — сказал он."
+        );
+    }
+
+    #[test]
+    fn sentence_tags_and_pos_suffixes() {
+        let mut tokens = tokens(&["_START_", "run", "_END_"]);
+        tokens[1].kind = NgramTokenKind::TaggedAsVerb;
+        let mut options = TextOptions::new(Corpus::English);
+        assert_eq!(detokenize(&tokens, &options), "run");
+        options.sentence_tags = SentenceTags::Keep;
+        options.pos_suffixes = true;
+        assert_eq!(detokenize(&tokens, &options), "_START_ run_VERB _END_");
+    }
+
+    #[test]
+    fn highlight_inserted_and_completed_tokens() {
+        let mut tokens = tokens(&["a", "<b>", "c_d"]);
+        tokens[1].inserted = true;
+        tokens[2].completed = true;
+        let mut options = TextOptions::new(Corpus::English);
+        options.highlighter = Some(&Html);
+        assert_eq!(
+            detokenize(&tokens, &options),
+            r#"a <span class="inserted">&lt;b&gt;</span> <span class="completed">c_d</span>"#
+        );
+        options.highlighter = Some(&Markdown);
+        assert_eq!(detokenize(&tokens, &options), r"a _<b>_ **c\_d**");
+        options.highlighter = Some(&Ansi);
+        assert_eq!(
+            detokenize(&tokens, &options),
+            "a \x1b[2m<b>\x1b[0m \x1b[4mc_d\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+use crate::{Ngram, NgramLength, TotalCounts};
+use std::ops::{Add, Div, Mul, RangeInclusive, Sub};
+use std::{fmt, iter, slice};
+
+/// Inclusive range of years.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct YearRange {
+    first: u16,
+    last: u16,
+}
+
+impl YearRange {
+    /// Panics if `first > last`.
+    pub fn new(first: u16, last: u16) -> Self {
+        assert!(first <= last, "invalid year range {first}-{last}");
+        Self { first, last }
+    }
+
+    pub fn first(&self) -> u16 {
+        self.first
+    }
+
+    pub fn last(&self) -> u16 {
+        self.last
+    }
+
+    pub fn num_years(&self) -> usize {
+        (self.last - self.first) as usize + 1
+    }
+
+    pub fn contains(&self, year: u16) -> bool {
+        self.first <= year && year <= self.last
+    }
+
+    pub fn contains_range(&self, other: YearRange) -> bool {
+        self.first <= other.first && other.last <= self.last
+    }
+
+    pub fn intersection(&self, other: YearRange) -> Option<YearRange> {
+        let first = self.first.max(other.first);
+        let last = self.last.min(other.last);
+        (first <= last).then_some(Self { first, last })
+    }
+
+    /// Smallest range that contains both ranges.
+    pub fn span(&self, other: YearRange) -> YearRange {
+        Self {
+            first: self.first.min(other.first),
+            last: self.last.max(other.last),
+        }
+    }
+
+    pub fn iter(&self) -> RangeInclusive<u16> {
+        self.first..=self.last
+    }
+}
+
+impl From<RangeInclusive<u16>> for YearRange {
+    fn from(range: RangeInclusive<u16>) -> Self {
+        Self::new(*range.start(), *range.end())
+    }
+}
+
+impl fmt::Display for YearRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
+/// Which value of [`NgramStat`](crate::NgramStat) a time series is built from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measure {
+    /// `abs_match_count`
+    Absolute,
+    /// `rel_match_count`
+    Relative,
+}
+
+/// Dense series of one value per year.
+///
+/// Binary operations between two series are evaluated over the intersection
+/// of their year ranges. Division yields zero in years where the divisor is
+/// zero, which is common for early years with no books in the corpus.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeSeries {
+    first_year: u16,
+    values: Vec<f64>,
+}
+
+impl TimeSeries {
+    pub fn new(first_year: u16, values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        assert!(
+            first_year as usize + values.len() - 1 <= u16::MAX as usize,
+            "time series exceeds year {}",
+            u16::MAX
+        );
+        Self { first_year, values }
+    }
+
+    pub fn zeros(years: YearRange) -> Self {
+        Self::new(years.first, vec![0.0; years.num_years()])
+    }
+
+    /// Builds a series over `years`, with zeros in years missing from `points`.
+    /// Points outside of `years` are ignored.
+    pub fn from_points<I>(years: YearRange, points: I) -> Self
+    where
+        I: IntoIterator<Item = (u16, f64)>,
+    {
+        let mut series = Self::zeros(years);
+        for (year, value) in points {
+            if years.contains(year) {
+                series.values[(year - years.first) as usize] = value;
+            }
+        }
+        series
+    }
+
+    /// Returns `None` for an empty series.
+    pub fn years(&self) -> Option<YearRange> {
+        (!self.values.is_empty()).then(|| YearRange {
+            first: self.first_year,
+            last: self.first_year + (self.values.len() - 1) as u16,
+        })
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, year: u16) -> Option<f64> {
+        let index = year.checked_sub(self.first_year)? as usize;
+        self.values.get(index).copied()
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            years: self.first_year..,
+            values: self.values.iter(),
+        }
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.values.iter().sum()
+    }
+
+    /// Returns the part of the series within `years`, which may be empty.
+    pub fn slice<R: Into<YearRange>>(&self, years: R) -> Self {
+        match self.years().and_then(|own| own.intersection(years.into())) {
+            Some(years) => {
+                let start = (years.first - self.first_year) as usize;
+                Self::new(
+                    years.first,
+                    self.values[start..start + years.num_years()].to_vec(),
+                )
+            }
+            None => Self::default(),
+        }
+    }
+
+    /// Returns a series over exactly `years`, truncating values outside and
+    /// filling missing years with zero.
+    pub fn reindex<R: Into<YearRange>>(&self, years: R) -> Self {
+        Self::from_points(years.into(), self.iter())
+    }
+
+    pub fn map<F: FnMut(f64) -> f64>(&self, f: F) -> Self {
+        Self::new(
+            self.first_year,
+            self.values.iter().copied().map(f).collect(),
+        )
+    }
+
+    fn zip_with<F: Fn(f64, f64) -> f64>(&self, rhs: &TimeSeries, f: F) -> Self {
+        let years = match (self.years(), rhs.years()) {
+            (Some(lhs), Some(rhs)) => lhs.intersection(rhs),
+            _ => None,
+        };
+        match years {
+            Some(years) => Self::new(
+                years.first,
+                years
+                    .iter()
+                    .map(|year| f(self.get(year).unwrap(), rhs.get(year).unwrap()))
+                    .collect(),
+            ),
+            None => Self::default(),
+        }
+    }
+}
+
+fn div_or_zero(lhs: f64, rhs: f64) -> f64 {
+    if rhs == 0.0 {
+        0.0
+    } else {
+        lhs / rhs
+    }
+}
+
+macro_rules! impl_series_op {
+    ($op:ident, $method:ident, $f:expr) => {
+        impl $op<&TimeSeries> for &TimeSeries {
+            type Output = TimeSeries;
+
+            fn $method(self, rhs: &TimeSeries) -> TimeSeries {
+                self.zip_with(rhs, $f)
+            }
+        }
+
+        impl $op<TimeSeries> for TimeSeries {
+            type Output = TimeSeries;
+
+            fn $method(self, rhs: TimeSeries) -> TimeSeries {
+                self.zip_with(&rhs, $f)
+            }
+        }
+
+        impl $op<f64> for &TimeSeries {
+            type Output = TimeSeries;
+
+            fn $method(self, rhs: f64) -> TimeSeries {
+                self.map(|lhs| $f(lhs, rhs))
+            }
+        }
+
+        impl $op<f64> for TimeSeries {
+            type Output = TimeSeries;
+
+            fn $method(self, rhs: f64) -> TimeSeries {
+                self.map(|lhs| $f(lhs, rhs))
+            }
+        }
+    };
+}
+
+impl_series_op!(Add, add, |lhs, rhs| lhs + rhs);
+impl_series_op!(Sub, sub, |lhs, rhs| lhs - rhs);
+impl_series_op!(Mul, mul, |lhs, rhs| lhs * rhs);
+impl_series_op!(Div, div, div_or_zero);
+
+/// Iterator over the `(year, value)` pairs of a [`TimeSeries`].
+pub struct Iter<'a> {
+    years: std::ops::RangeFrom<u16>,
+    values: slice::Iter<'a, f64>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = (u16, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = *self.values.next()?;
+        Some((self.years.next().unwrap(), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a TimeSeries {
+    type Item = (u16, f64);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl iter::FromIterator<(u16, f64)> for TimeSeries {
+    /// Collects consecutive `(year, value)` pairs. Panics on gaps between years.
+    fn from_iter<I: IntoIterator<Item = (u16, f64)>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let Some((first_year, value)) = iter.next() else {
+            return Self::default();
+        };
+        let mut values = vec![value];
+        for (year, value) in iter {
+            assert_eq!(
+                year as usize,
+                first_year as usize + values.len(),
+                "years must be consecutive"
+            );
+            values.push(value);
+        }
+        Self::new(first_year, values)
+    }
+}
+
+impl Ngram {
+    /// Returns the zero-filled series from the first to the last year with a
+    /// recorded match, or an empty series if there are no stats.
+    pub fn time_series(&self, measure: Measure) -> TimeSeries {
+        let years =
+            self.stats
+                .iter()
+                .map(|stat| stat.year)
+                .fold(None, |years: Option<YearRange>, year| {
+                    let year = YearRange::new(year, year);
+                    Some(years.map_or(year, |years| years.span(year)))
+                });
+        match years {
+            Some(years) => TimeSeries::from_points(
+                years,
+                self.stats.iter().map(|stat| match measure {
+                    Measure::Absolute => (stat.year, stat.abs_match_count as f64),
+                    Measure::Relative => (stat.year, stat.rel_match_count),
+                }),
+            ),
+            None => TimeSeries::default(),
+        }
+    }
+}
+
+impl TotalCounts {
+    /// Years covered by the corpus.
+    pub fn years(&self) -> YearRange {
+        YearRange::new(self.min_year, self.max_year)
+    }
+
+    /// Returns the total match counts of all ngrams of length `len` from
+    /// `min_year` to `max_year`.
+    pub fn series(&self, len: NgramLength) -> TimeSeries {
+        self.counts(len)
+            .map(|(year, count)| (year, count as f64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Measure, TimeSeries, YearRange};
+    use crate::testing::{ngram, total_counts};
+    use crate::{NgramLength, NgramStat};
+
+    #[test]
+    fn ngram_time_series_is_zero_filled() {
+        let empty = ngram(Vec::new());
+        assert!(empty.time_series(Measure::Absolute).is_empty());
+
+        let ngram = ngram(vec![
+            NgramStat::new(1900, 3, 0.3),
+            NgramStat::new(1903, 5, 0.5),
+        ]);
+        let absolute = ngram.time_series(Measure::Absolute);
+        assert_eq!(absolute.years(), Some(YearRange::new(1900, 1903)));
+        assert_eq!(absolute.values(), [3.0, 0.0, 0.0, 5.0]);
+        let relative = ngram.time_series(Measure::Relative);
+        assert_eq!(relative.values(), [0.3, 0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn total_counts_series_starts_at_min_year() {
+        let counts = total_counts(1470, &[1, 2, 3]);
+        let series = counts.series(NgramLength::new(2).unwrap());
+        assert_eq!(series.years(), Some(YearRange::new(1470, 2019)));
+        assert_eq!(series.get(1472), Some(3.0));
+        assert_eq!(series.get(2019), Some(0.0));
+        assert_eq!(series.get(2020), None);
+        assert_eq!(series.get(1469), None);
+    }
+
+    #[test]
+    fn slice_and_reindex() {
+        let series = TimeSeries::new(1900, vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(series.slice(1901..=1902).values(), [2.0, 3.0]);
+        assert_eq!(series.slice(1899..=1900).values(), [1.0]);
+        assert!(series.slice(1950..=1960).is_empty());
+        let reindexed = series.reindex(1902..=1905);
+        assert_eq!(reindexed.years(), Some(YearRange::new(1902, 1905)));
+        assert_eq!(reindexed.values(), [3.0, 4.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn arithmetic_uses_intersection_of_years() {
+        let lhs = TimeSeries::new(1900, vec![1.0, 2.0, 3.0]);
+        let rhs = TimeSeries::new(1901, vec![10.0, 0.0, 30.0]);
+        assert_eq!(&lhs + &rhs, TimeSeries::new(1901, vec![12.0, 3.0]));
+        assert_eq!(&lhs - &rhs, TimeSeries::new(1901, vec![-8.0, 3.0]));
+        assert_eq!(&lhs * &rhs, TimeSeries::new(1901, vec![20.0, 0.0]));
+        assert_eq!(&lhs / &rhs, TimeSeries::new(1901, vec![0.2, 0.0]));
+        assert_eq!(
+            lhs.clone() * 2.0,
+            TimeSeries::new(1900, vec![2.0, 4.0, 6.0])
+        );
+        assert!((lhs / TimeSeries::new(1950, vec![1.0])).is_empty());
+    }
+
+    #[test]
+    fn iterate_year_value_pairs() {
+        let series = TimeSeries::new(2000, vec![1.0, 2.0]);
+        let pairs: Vec<_> = series.iter().collect();
+        assert_eq!(pairs, [(2000, 1.0), (2001, 2.0)]);
+        assert_eq!(pairs.into_iter().collect::<TimeSeries>(), series);
+    }
+}
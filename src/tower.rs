@@ -0,0 +1,391 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! [`tower::Service`](::tower::Service) integration.
+//!
+//! [`NgramsService`] exposes the ngrams.dev operations as a service over
+//! [`Operation`], so that standard tower layers (timeouts, concurrency limits,
+//! load shedding, ...) can be stacked on top of it. [`Client::from_service`]
+//! turns any such service, layered or not, back into a regular [`Client`].
+
+use crate::metrics::Metrics;
+use crate::middleware::{Request, Response};
+use crate::transport::{BoxFuture, Transport};
+use crate::{
+    internal, Client, Corpus, CorpusInfo, Error, ErrorKind, Ngram, NgramLite, Page, QueryToken,
+    SearchOptions, TotalCounts,
+};
+use ::tower::util::BoxCloneSyncService;
+use ::tower::{BoxError, Service, ServiceExt};
+use serde::Serialize;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// Typed request understood by [`NgramsService`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// Fetches a single search result page. `start` is the `next_page_token`
+    /// of the previous page, or `None` for the first page.
+    SearchPage {
+        corpus: Corpus,
+        query: String,
+        options: SearchOptions,
+        start: Option<String>,
+    },
+    GetNgram {
+        corpus: Corpus,
+        id: String,
+    },
+    GetCorpusInfo {
+        corpus: Corpus,
+    },
+    GetTotalCounts {
+        corpus: Corpus,
+    },
+}
+
+/// Typed response produced by [`NgramsService`], one variant per [`Operation`].
+#[derive(Debug)]
+pub enum Outcome {
+    SearchPage(SearchPage),
+    Ngram(Option<Ngram>),
+    CorpusInfo(CorpusInfo),
+    TotalCounts(Box<TotalCounts>),
+}
+
+#[derive(Debug)]
+pub struct SearchPage {
+    pub page: Page,
+    pub next_page_token: Option<String>,
+}
+
+/// Service that executes an [`Operation`] with a [`Client`].
+#[derive(Clone)]
+pub struct NgramsService {
+    client: Client,
+}
+
+impl NgramsService {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Service<Operation> for NgramsService {
+    type Response = Outcome;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Outcome, Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, operation: Operation) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move {
+            match operation {
+                Operation::SearchPage {
+                    corpus,
+                    query,
+                    options,
+                    start,
+                } => {
+                    let res =
+                        internal::search_page(&client, corpus, &query, options, start.as_deref())
+                            .await
+                            .and_then(|text| to_search_page(&text));
+                    client.observe(res).map(Outcome::SearchPage)
+                }
+                Operation::GetNgram { corpus, id } => {
+                    client.get_ngram(corpus, &id).await.map(Outcome::Ngram)
+                }
+                Operation::GetCorpusInfo { corpus } => client
+                    .get_corpus_info(corpus)
+                    .await
+                    .map(Outcome::CorpusInfo),
+                Operation::GetTotalCounts { corpus } => client
+                    .get_total_counts(corpus)
+                    .await
+                    .map(|counts| Outcome::TotalCounts(Box::new(counts))),
+            }
+        })
+    }
+}
+
+fn to_search_page(text: &str) -> Result<SearchPage, Error> {
+    let res = serde_json::from_str::<internal::SearchResult>(text)?;
+    Ok(SearchPage {
+        page: Page {
+            query_tokens: res.query_tokens.iter().map(Into::into).collect(),
+            ngrams: res.ngrams.iter().map(Into::into).collect(),
+        },
+        next_page_token: res.next_page_token.map(Into::into),
+    })
+}
+
+impl Client {
+    /// Creates a client that sends every operation through `service`.
+    ///
+    /// The service takes the place of the transport: requests still pass
+    /// through the middleware chain and are counted in the client metrics.
+    /// Requests that wait for the service to become ready, e.g. behind a
+    /// rate limit layer, are counted as rate limit waits.
+    /// Errors returned by `service` that are not an [`Error`] themselves, such
+    /// as a timeout or an overloaded layer, are reported as
+    /// [`ErrorKind::Connection`].
+    pub fn from_service<S>(service: S) -> Self
+    where
+        S: Service<Operation, Response = Outcome> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        let service = service.map_err(|err: S::Error| match err.into().downcast::<Error>() {
+            Ok(err) => *err,
+            Err(err) => Error::new(ErrorKind::Connection, Some(err)),
+        });
+        let metrics = Arc::new(Metrics::default());
+        let mut client = Self::with_transport(ServiceTransport {
+            service: BoxCloneSyncService::new(service),
+            metrics: metrics.clone(),
+        });
+        client.metrics = metrics;
+        client
+    }
+}
+
+/// Transport that turns requests into [`Operation`]s and the [`Outcome`]s of
+/// a service back into responses.
+struct ServiceTransport {
+    service: BoxCloneSyncService<Operation, Outcome, Error>,
+    /// Metrics of the client using this transport.
+    metrics: Arc<Metrics>,
+}
+
+impl Transport for ServiceTransport {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move {
+            let mut service = self.service.clone();
+            let start = Instant::now();
+            let mut waited = false;
+            poll_fn(|cx| {
+                let poll = service.poll_ready(cx);
+                waited |= poll.is_pending();
+                poll
+            })
+            .await?;
+            if waited {
+                self.metrics.record_rate_limit_wait(start.elapsed());
+            }
+            let outcome = service.call(to_operation(request)).await?;
+            to_response(outcome)
+        })
+    }
+}
+
+fn to_operation(request: Request) -> Operation {
+    let corpus = request.corpus;
+    match request.resource.as_str() {
+        "search" => {
+            let mut query = String::new();
+            let mut options = SearchOptions {
+                max_page_count: 1,
+                ..Default::default()
+            };
+            let mut start = None;
+            for (key, value) in request.query {
+                match key.as_str() {
+                    "query" => query = value,
+                    "limit" => options.max_page_size = value.parse().unwrap_or_default(),
+                    "flags" => options = SearchOptions::from_flags(&value, options),
+                    "start" => start = Some(value),
+                    _ => {}
+                }
+            }
+            Operation::SearchPage {
+                corpus,
+                query,
+                options,
+                start,
+            }
+        }
+        "info" => Operation::GetCorpusInfo { corpus },
+        "total_counts" => Operation::GetTotalCounts { corpus },
+        _ => Operation::GetNgram {
+            corpus,
+            id: request.resource,
+        },
+    }
+}
+
+fn to_response(outcome: Outcome) -> Result<Response, Error> {
+    let body = match outcome {
+        Outcome::SearchPage(page) => serde_json::to_string(&SearchResult {
+            query_tokens: &page.page.query_tokens,
+            ngrams: &page.page.ngrams,
+            next_page_token: page.next_page_token.as_deref(),
+        })?,
+        Outcome::Ngram(None) => return Ok(Response::new(404, "")),
+        Outcome::Ngram(Some(ngram)) => serde_json::to_string(&ngram)?,
+        Outcome::CorpusInfo(info) => serde_json::to_string(&info)?,
+        Outcome::TotalCounts(counts) => serde_json::to_string(&counts)?,
+    };
+    Ok(Response::new(200, body))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult<'a> {
+    query_tokens: &'a [QueryToken],
+    ngrams: &'a [NgramLite],
+    next_page_token: Option<&'a str>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NgramsService, Operation, Outcome};
+    use crate::metrics::Endpoint;
+    use crate::middleware::{Middleware, Request, Response};
+    use crate::testing::{Canned, Fixtures};
+    use crate::{Client, Corpus, Error, ErrorKind, SearchOptions};
+    use ::tower::{service_fn, BoxError, Service, ServiceExt};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    struct Log(Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Log {
+        fn before_request(&self, request: &mut Request) -> Option<Response> {
+            self.0.lock().unwrap().push(request.resource.clone());
+            None
+        }
+
+        fn after_response(&self, response: &Response) {
+            self.0.lock().unwrap().push(response.status.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn service_executes_typed_operations() {
+        let service =
+            NgramsService::new(Client::with_transport(Fixtures::default()).with_middleware(Canned));
+        let operation = Operation::GetNgram {
+            corpus: Corpus::English,
+            id: "x".into(),
+        };
+        match service.clone().oneshot(operation).await.unwrap() {
+            Outcome::Ngram(ngram) => assert_eq!(ngram, None),
+            other => panic!("{other:?}"),
+        }
+        let operation = Operation::SearchPage {
+            corpus: Corpus::English,
+            query: "hello".into(),
+            options: SearchOptions::default(),
+            start: None,
+        };
+        match service.oneshot(operation).await.unwrap() {
+            Outcome::SearchPage(page) => {
+                assert_eq!(page.page.query_tokens.len(), 1);
+                assert_eq!(page.next_page_token.as_deref(), Some("abc"));
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn client_is_built_on_top_of_service() {
+        let service =
+            NgramsService::new(Client::with_transport(Fixtures::default()).with_middleware(Canned));
+        let client = Client::from_service(service);
+
+        let options = SearchOptions {
+            max_page_count: 2,
+            ..Default::default()
+        };
+        let mut pages = client.search("hello", Corpus::English, options);
+        let mut num_pages = 0;
+        while let Some(page) = pages.next().await {
+            assert_eq!(page.unwrap().query_tokens[0].text, "hello");
+            num_pages += 1;
+        }
+        assert_eq!(num_pages, 2);
+        assert_eq!(client.get_ngram(Corpus::English, "x").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn service_client_runs_middleware_and_metrics() {
+        let service =
+            NgramsService::new(Client::with_transport(Fixtures::default()).with_middleware(Canned));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::from_service(service).with_middleware(Log(log.clone()));
+
+        let options = SearchOptions {
+            max_page_count: 1,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let mut pages = client.search("hello", Corpus::English, options);
+        while let Some(page) = pages.next().await {
+            page.unwrap();
+        }
+        assert_eq!(client.get_ngram(Corpus::English, "x").await.unwrap(), None);
+        assert_eq!(*log.lock().unwrap(), ["search", "200", "x", "404"]);
+
+        let metrics = client.metrics();
+        assert_eq!(metrics.requests[&Endpoint::Search], 1);
+        assert_eq!(metrics.requests[&Endpoint::Ngram], 1);
+        assert_eq!(metrics.status_codes[&404], 1);
+    }
+
+    /// Service that is not ready the first time it is polled.
+    #[derive(Clone)]
+    struct Throttled {
+        inner: NgramsService,
+        throttled: Arc<AtomicBool>,
+    }
+
+    impl Service<Operation> for Throttled {
+        type Response = Outcome;
+        type Error = Error;
+        type Future = <NgramsService as Service<Operation>>::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            if self.throttled.swap(false, Ordering::SeqCst) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, operation: Operation) -> Self::Future {
+            self.inner.call(operation)
+        }
+    }
+
+    #[tokio::test]
+    async fn service_client_counts_rate_limit_waits() {
+        let service = Throttled {
+            inner: NgramsService::new(
+                Client::with_transport(Fixtures::default()).with_middleware(Canned),
+            ),
+            throttled: Arc::new(AtomicBool::new(true)),
+        };
+        let client = Client::from_service(service);
+        assert_eq!(client.get_ngram(Corpus::English, "x").await.unwrap(), None);
+        assert_eq!(client.get_ngram(Corpus::English, "y").await.unwrap(), None);
+        assert_eq!(client.metrics().rate_limit_waits, 1);
+    }
+
+    #[tokio::test]
+    async fn foreign_service_errors_become_connection_errors() {
+        let service =
+            service_fn(|_: Operation| async { Err::<Outcome, BoxError>("overloaded".into()) });
+        let client = Client::from_service(service);
+        let err = client.get_corpus_info(Corpus::English).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Connection);
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Pluggable HTTP transport.
+//!
+//! The client itself does not depend on any async runtime. With the default
+//! `rustls-tls` or the `native-tls` feature, requests are sent with
+//! [`reqwest`](https://docs.rs/reqwest), which requires a tokio runtime. Users
+//! of other runtimes can disable the default features and provide their own
+//! [`Transport`] via [`Client::with_transport`](crate::Client::with_transport).
+
+use crate::middleware::{Request, Response};
+use crate::Error;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Sends a GET request and returns the response status and body.
+///
+/// Implementations should report transport failures as
+/// [`Error::connection`] and leave non-2xx status codes to the client.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>>;
+}
+
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    inner: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new(inner: reqwest::Client) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move {
+            let mut builder = self.inner.get(request.url()).query(&request.query);
+            for (key, value) in &request.headers {
+                builder = builder.header(key, value);
+            }
+            let res = builder.send().await.map_err(Error::connection)?;
+            let status = res.status().as_u16();
+            let body = res.text().await.map_err(Error::connection)?;
+            Ok(Response { status, body })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoxFuture, Transport};
+    use crate::middleware::{Request, Response};
+    use crate::{Client, Corpus, Error};
+    use std::sync::{Arc, Mutex};
+
+    struct Recording(Arc<Mutex<Vec<String>>>);
+
+    impl Transport for Recording {
+        fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+            self.0.lock().unwrap().push(request.url());
+            Box::pin(async { Ok(Response::new(404, "")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn client_sends_through_custom_transport() {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::with_transport(Recording(urls.clone()));
+        assert_eq!(client.get_ngram(Corpus::German, "abc").await.unwrap(), None);
+        assert_eq!(*urls.lock().unwrap(), ["https://api.ngrams.dev/ger/abc"]);
+        assert_eq!(client.metrics().status_codes[&404], 1);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright Martin Trenkmann
+// https://ngrams.dev
+// License: MIT
+
+//! Aggregation of case and spelling variants into one concept.
+//!
+//! A case-insensitive search returns `Internet`, `internet` and `INTERNET` as
+//! separate ngrams. Grouping them by a normalized form sums their counts and
+//! their yearly series client-side, unlike `SearchOptions::collapse_result`,
+//! which only keeps one representative on the server.
+
+use crate::{Client, Corpus, Error, Measure, Ngram, NgramLite, TimeSeries, YearRange};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Steps applied to every token of an ngram, in the order of the fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Normalization {
+    /// Compatibility composition, e.g. `ﬁ` becomes `fi` and `²` becomes `2`.
+    pub nfkc: bool,
+    pub case_fold: bool,
+    /// Removes combining marks after canonical decomposition, e.g. `café`
+    /// becomes `cafe`. Note that this also turns `ü` into `u`.
+    pub strip_diacritics: bool,
+    /// Maps normalized tokens to their canonical spelling, e.g. `colour` to
+    /// `color`.
+    pub variants: HashMap<String, String>,
+}
+
+impl Normalization {
+    /// Case folding only.
+    pub fn case_insensitive() -> Self {
+        Self {
+            case_fold: true,
+            ..Default::default()
+        }
+    }
+
+    /// All built-in steps.
+    pub fn aggressive() -> Self {
+        Self {
+            nfkc: true,
+            case_fold: true,
+            strip_diacritics: true,
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Adds a custom variant. `variant` must already be normalized by the
+    /// other steps to ever match.
+    pub fn with_variant(
+        mut self,
+        variant: impl Into<String>,
+        canonical: impl Into<String>,
+    ) -> Self {
+        self.variants.insert(variant.into(), canonical.into());
+        self
+    }
+
+    pub fn normalize_token(&self, token: &str) -> String {
+        let mut token = if self.nfkc {
+            token.nfkc().collect()
+        } else {
+            token.to_string()
+        };
+        if self.case_fold {
+            token = token.to_lowercase();
+        }
+        if self.strip_diacritics {
+            token = token
+                .nfd()
+                .filter(|&c| !is_combining_mark(c))
+                .nfc()
+                .collect();
+        }
+        match self.variants.get(&token) {
+            Some(canonical) => canonical.clone(),
+            None => token,
+        }
+    }
+
+    /// Normalized tokens of `ngram` separated by a space.
+    pub fn key(&self, ngram: &NgramLite) -> String {
+        let tokens: Vec<_> = ngram
+            .tokens
+            .iter()
+            .map(|token| self.normalize_token(&token.text))
+            .collect();
+        tokens.join(" ")
+    }
+}
+
+/// Ngrams with the same normalized form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariantGroup {
+    /// The normalized form.
+    pub key: String,
+    /// Most frequent first.
+    pub members: Vec<Ngram>,
+    /// Sum of the members' `abs_total_match_count`.
+    pub total: u64,
+    /// Sum of the members' absolute match counts over all years in which any
+    /// member occurs.
+    pub series: TimeSeries,
+}
+
+impl VariantGroup {
+    pub(crate) fn new(key: String, mut members: Vec<Ngram>) -> Self {
+        members.sort_by_key(|ngram| Reverse(ngram.abs_total_match_count));
+        let series: Vec<_> = members
+            .iter()
+            .map(|ngram| ngram.time_series(Measure::Absolute))
+            .collect();
+        let years = series
+            .iter()
+            .filter_map(TimeSeries::years)
+            .reduce(|a, b| a.span(b));
+        let series = match years {
+            Some(years) => series
+                .iter()
+                .fold(TimeSeries::zeros(years), |sum, s| &sum + &s.reindex(years)),
+            None => TimeSeries::default(),
+        };
+        Self {
+            key,
+            total: members
+                .iter()
+                .map(|ngram| ngram.abs_total_match_count)
+                .sum(),
+            members,
+            series,
+        }
+    }
+
+    /// Years covered by [`series`](Self::series).
+    pub fn years(&self) -> Option<YearRange> {
+        self.series.years()
+    }
+}
+
+impl Client {
+    /// Fetches the full stats of `ngrams` and groups them by their normalized
+    /// form, the most frequent group first. Ngrams that no longer exist are
+    /// skipped.
+    pub async fn group_variants<'a, I>(
+        &self,
+        corpus: Corpus,
+        ngrams: I,
+        normalization: &Normalization,
+    ) -> Result<Vec<VariantGroup>, Error>
+    where
+        I: IntoIterator<Item = &'a NgramLite>,
+    {
+        let mut keys = Vec::new();
+        let mut members: HashMap<String, Vec<Ngram>> = HashMap::new();
+        for lite in ngrams {
+            let Some(ngram) = self.get_ngram(corpus, &lite.id).await? else {
+                continue;
+            };
+            let key = normalization.key(lite);
+            if !members.contains_key(&key) {
+                keys.push(key.clone());
+            }
+            members.entry(key).or_default().push(ngram);
+        }
+        let mut groups: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                let members = members.remove(&key).unwrap_or_default();
+                VariantGroup::new(key, members)
+            })
+            .collect();
+        groups.sort_by_key(|group| Reverse(group.total));
+        Ok(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Normalization;
+    use crate::testing::{ngram_lite, Fixtures};
+    use crate::{Client, Corpus, NgramStat, SearchOptions};
+
+    #[test]
+    fn normalization_steps() {
+        let normalization = Normalization::aggressive().with_variant("colour", "color");
+        assert_eq!(normalization.normalize_token("Café"), "cafe");
+        assert_eq!(normalization.normalize_token("ﬁne"), "fine");
+        assert_eq!(normalization.normalize_token("COLOUR"), "color");
+        assert_eq!(normalization.normalize_token("Ärger"), "arger");
+
+        let case_only = Normalization::case_insensitive();
+        assert_eq!(case_only.normalize_token("Café"), "café");
+        assert_eq!(
+            case_only.key(&ngram_lite(&["The", "INTERNET"], 1)),
+            "the internet"
+        );
+        assert_eq!(Normalization::default().normalize_token("ﬁne"), "ﬁne");
+    }
+
+    #[tokio::test]
+    async fn group_case_variants() {
+        let fixtures = Fixtures::default().found(
+            "int*net",
+            &[
+                (&["Internet"], vec![NgramStat::new(1990, 5, 0.0)]),
+                (
+                    &["internet"],
+                    vec![NgramStat::new(1992, 10, 0.0), NgramStat::new(1993, 20, 0.0)],
+                ),
+                (&["INTERNET"], vec![NgramStat::new(1993, 1, 0.0)]),
+                (&["intranet"], vec![NgramStat::new(1995, 3, 0.0)]),
+            ],
+        );
+        let client = Client::with_transport(fixtures);
+        let mut pages = client.search("int*net", Corpus::English, SearchOptions::default());
+        let lites = pages.next().await.unwrap().unwrap().to_page().ngrams;
+        let groups = client
+            .group_variants(Corpus::English, &lites, &Normalization::case_insensitive())
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "internet");
+        assert_eq!(groups[0].total, 36);
+        assert_eq!(groups[0].members.len(), 3);
+        assert_eq!(groups[0].members[0].id, "internet");
+        assert_eq!(groups[0].series.values(), [5.0, 0.0, 10.0, 21.0]);
+        assert_eq!(groups[1].key, "intranet");
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "reqwest")]
+
 use ngrams::{Client, Corpus, CorpusInfo, CorpusStat};
 
 #[tokio::test]
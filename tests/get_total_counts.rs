@@ -1,3 +1,5 @@
+#![cfg(feature = "reqwest")]
+
 use ngrams::{Client, Corpus};
 
 #[tokio::test]